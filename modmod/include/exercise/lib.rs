@@ -0,0 +1,13 @@
+pub fn solve() {
+    todo!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        solve();
+    }
+}
@@ -0,0 +1,17 @@
+fn main() {
+    solve();
+}
+
+fn solve() {
+    todo!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        solve();
+    }
+}
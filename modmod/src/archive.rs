@@ -0,0 +1,258 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    exercises::RenderedExercises,
+    io::{PathExt, WriteExt},
+    slug::to_tag,
+    RenderReport,
+};
+
+pub const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ArchiveExercisesError;
+
+impl fmt::Display for ArchiveExercisesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to archive exercises")
+    }
+}
+
+impl error_stack::Context for ArchiveExercisesError {}
+
+/// The archives of the exercises
+#[derive(Debug, Default)]
+pub struct RenderedArchives {
+    /// The paths of the archives of each module by module index, relative to the output directory
+    pub module_archives: BTreeMap<usize, Vec<PathBuf>>,
+    pub report: RenderReport,
+}
+
+/// The name of the archives containing the exercises of the whole track, without extension
+pub fn track_archive_name(track_name: &str) -> String {
+    format!("{}-exercises", to_tag(track_name))
+}
+
+/// Package the rendered exercises of each module, as well as those of the whole track,
+/// into `.tar.gz` and `.zip` archives in the `archives` folder of the output directory.
+/// A `SHA256SUMS` file listing the checksums of all archives is written alongside.
+pub fn archive_exercises(
+    exercises: &RenderedExercises,
+    track_name: &str,
+    output_dir: &Path,
+) -> Result<RenderedArchives, ArchiveExercisesError> {
+    let archives_dir = output_dir.join("archives");
+    archives_dir.create_dir_all()?;
+    let mut module_archives = BTreeMap::new();
+    let mut report = RenderReport::default();
+    let mut checksums = String::new();
+
+    let track_archive = (track_archive_name(track_name), Path::new("exercises"));
+    let archived_dirs = exercises
+        .module_paths
+        .iter()
+        .map(|(module_index, module_path)| {
+            let name = module_path.file_name().unwrap().to_string_lossy();
+            (
+                Some(*module_index),
+                (name.into_owned(), module_path.as_path()),
+            )
+        })
+        .chain([(None, track_archive)]);
+
+    for (module_index, (name, dir)) in archived_dirs {
+        let src_dir = output_dir.join(dir);
+        let root = Path::new(dir.file_name().unwrap());
+        let tar_gz = archives_dir.join(format!("{name}.tar.gz"));
+        let zip = archives_dir.join(format!("{name}.zip"));
+        write_tar_gz(&src_dir, root, &tar_gz)?;
+        write_zip(&src_dir, root, &zip)?;
+        report.files.extend([tar_gz.clone(), zip.clone()]);
+
+        for archive in [&tar_gz, &zip] {
+            checksums += &format!(
+                "{}  {}\n",
+                sha256(archive)?,
+                archive.file_name().unwrap().to_string_lossy()
+            );
+        }
+
+        if let Some(module_index) = module_index {
+            module_archives.insert(
+                module_index,
+                [tar_gz, zip]
+                    .map(|a| a.strip_prefix(output_dir).unwrap().to_path_buf())
+                    .to_vec(),
+            );
+        }
+    }
+
+    report
+        .create_file(archives_dir.join(CHECKSUMS_FILE))?
+        .write_all(checksums)?;
+
+    Ok(RenderedArchives {
+        module_archives,
+        report,
+    })
+}
+
+/// Write the contents of `src_dir` into a gzipped tarball at `dest`, under the folder `root`
+fn write_tar_gz(src_dir: &Path, root: &Path, dest: &Path) -> Result<(), ArchiveExercisesError> {
+    let file = dest.create_file()?;
+    let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+        file,
+        flate2::Compression::default(),
+    ));
+    tar.append_dir_all(root, src_dir)
+        .and_then(|_| tar.into_inner())
+        .and_then(|gz| gz.finish())
+        .into_report()
+        .attach_printable_lazy(|| format!("Error writing archive {}", dest.to_string_lossy()))
+        .change_context(ArchiveExercisesError)?;
+    Ok(())
+}
+
+/// Write the contents of `src_dir` into a zip archive at `dest`, under the folder `root`
+fn write_zip(src_dir: &Path, root: &Path, dest: &Path) -> Result<(), ArchiveExercisesError> {
+    let mut zip = zip::ZipWriter::new(dest.create_file::<ArchiveExercisesError>()?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut files = src_dir.get_dir_content::<ArchiveExercisesError>()?.files;
+    files.sort();
+
+    for file in files {
+        let relative_path = Path::new(&file).strip_prefix(src_dir).unwrap();
+        let name = root
+            .join(relative_path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        zip.start_file(name, options)
+            .into_report()
+            .change_context(ArchiveExercisesError)?;
+        io::copy(&mut file.open_file::<ArchiveExercisesError>()?, &mut zip)
+            .into_report()
+            .change_context(ArchiveExercisesError)?;
+    }
+
+    zip.finish()
+        .into_report()
+        .attach_printable_lazy(|| format!("Error writing archive {}", dest.to_string_lossy()))
+        .change_context(ArchiveExercisesError)?;
+    Ok(())
+}
+
+fn sha256(path: &Path) -> Result<String, ArchiveExercisesError> {
+    let mut hasher = Sha256::new();
+    let mut file: File = path.open_file()?;
+    io::copy(&mut file, &mut hasher)
+        .into_report()
+        .change_context(ArchiveExercisesError)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io::Read};
+
+    use super::*;
+    use crate::{
+        io::{test_dir, write_test_file},
+        RenderReport,
+    };
+
+    #[test]
+    fn archives_contain_exercises_and_checksums() {
+        let output_dir = test_dir("archives");
+        write_test_file(
+            output_dir.join("exercises/1-intro/1-basics/1-hello/src/main.rs"),
+            "fn main() {}\n",
+        );
+        write_test_file(
+            output_dir.join("exercises/2-more/1-traits/1-shapes/Cargo.toml"),
+            "",
+        );
+        let exercises = RenderedExercises {
+            exercise_paths: HashMap::new(),
+            module_paths: BTreeMap::from([
+                (1, PathBuf::from("exercises/1-intro")),
+                (2, PathBuf::from("exercises/2-more")),
+            ]),
+            report: RenderReport::default(),
+        };
+
+        let archives = archive_exercises(&exercises, "Rust Intro", &output_dir).unwrap();
+        assert_eq!(archives.report.files.len(), 7);
+        assert_eq!(
+            archives.module_archives[&1],
+            [
+                PathBuf::from("archives/1-intro.tar.gz"),
+                PathBuf::from("archives/1-intro.zip")
+            ]
+        );
+
+        let archives_dir = output_dir.join("archives");
+        let tar_file = File::open(archives_dir.join("rust-intro-exercises.tar.gz")).unwrap();
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(tar_file));
+        let mut tar_files: Vec<_> = tar
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap())
+            .filter(|e| e.header().entry_type().is_file())
+            .map(|e| e.path().unwrap().to_string_lossy().into_owned())
+            .collect();
+        tar_files.sort();
+        assert_eq!(
+            tar_files,
+            [
+                "exercises/1-intro/1-basics/1-hello/src/main.rs",
+                "exercises/2-more/1-traits/1-shapes/Cargo.toml"
+            ]
+        );
+
+        let zip_file = File::open(archives_dir.join("1-intro.zip")).unwrap();
+        let mut zip = zip::ZipArchive::new(zip_file).unwrap();
+        assert_eq!(zip.len(), 1);
+        let mut main_rs = String::new();
+        zip.by_name("1-intro/1-basics/1-hello/src/main.rs")
+            .unwrap()
+            .read_to_string(&mut main_rs)
+            .unwrap();
+        assert_eq!(main_rs, "fn main() {}\n");
+
+        let checksums = std::fs::read_to_string(archives_dir.join(CHECKSUMS_FILE)).unwrap();
+        let names: Vec<_> = checksums
+            .lines()
+            .map(|line| {
+                let (checksum, name) = line.split_once("  ").unwrap();
+                assert_eq!(checksum, sha256(&archives_dir.join(name)).unwrap());
+                name
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "1-intro.tar.gz",
+                "1-intro.zip",
+                "2-more.tar.gz",
+                "2-more.zip",
+                "rust-intro-exercises.tar.gz",
+                "rust-intro-exercises.zip"
+            ]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::snippets::{check_snippets, CheckSnippetsOptions, SnippetOutcome};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(
+        long = "edition",
+        help = "The edition to compile snippets with, unless they specify one",
+        default_value = "2021"
+    )]
+    edition: String,
+    #[arg(
+        long = "timeout",
+        help = "Maximum number of seconds a single snippet may run",
+        default_value_t = 10
+    )]
+    timeout: u64,
+}
+
+pub fn run(args: Args) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        edition,
+        timeout,
+    } = args;
+
+    let track =
+        modmod::Track::load_toml_def(track_toml_path).change_context(ModModError::default())?;
+    // Compile in a folder of our own, so that concurrent runs don't overwrite each other's snippets
+    let work_dir = std::env::temp_dir().join(format!("modmod-snippets-{}", std::process::id()));
+    let checks = check_snippets(
+        &track,
+        CheckSnippetsOptions {
+            work_dir: &work_dir,
+            edition: &edition,
+            timeout: Duration::from_secs(timeout),
+            on_check: Some(&|location| println!("Checking snippet at {location}")),
+        },
+    );
+    std::fs::remove_dir_all(&work_dir).ok();
+    let checks = checks.change_context(ModModError::default())?;
+
+    let failures: Vec<_> = checks.iter().filter(|c| c.is_failure()).collect();
+    for check in failures.iter() {
+        if let SnippetOutcome::Failed { reason, output } = &check.outcome {
+            eprintln!(
+                "\n{}:{}: snippet {reason}\n{output}",
+                check.file.to_string_lossy(),
+                check.line
+            );
+        }
+    }
+    let mut skipped = 0;
+    for check in checks.iter() {
+        if let SnippetOutcome::Skipped { crates } = &check.outcome {
+            println!(
+                "{}:{}: snippet skipped, as it uses {}",
+                check.file.to_string_lossy(),
+                check.line,
+                crates.join(", ")
+            );
+            skipped += 1;
+        }
+    }
+    let ignored = checks
+        .iter()
+        .filter(|c| c.outcome == SnippetOutcome::Ignored)
+        .count();
+    println!(
+        "{} snippets checked: {} passed, {} failed, {ignored} ignored, {skipped} skipped",
+        checks.len(),
+        checks.len() - failures.len() - ignored - skipped,
+        failures.len()
+    );
+
+    if !failures.is_empty() {
+        return Err(
+            ModModError::report().attach_printable(format!("{} snippets failed", failures.len()))
+        );
+    }
+    Ok(())
+}
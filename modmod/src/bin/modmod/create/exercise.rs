@@ -1,9 +1,9 @@
-use std::ffi::OsStr;
+use std::{ffi::OsStr, path::Path};
 
 use error_stack::IntoReport;
-use modmod::load::ExerciseDef;
+use modmod::{load::ExerciseDef, slug::to_tag};
 
-use super::imports::*;
+use super::{imports::*, validate_topic};
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct CreateExercise {
@@ -15,10 +15,23 @@ pub struct CreateExercise {
     )]
     index: Option<usize>,
     name: String,
+    #[arg(long = "lib", help = "Create a library crate", conflicts_with = "bin")]
+    lib: bool,
+    #[arg(long = "bin", help = "Create a binary crate. This is the default")]
+    bin: bool,
+    #[arg(
+        long = "template",
+        help = "A folder whose contents are copied into the new exercise crate, replacing the generated files. Defaults to the nearest templates/exercise folder above the topic"
+    )]
+    template: Option<PathBuf>,
 }
 
+const DESCRIPTION_STUB: &str = include_str!("../../../../include/exercise/description.md");
+const LIB_STUB: &str = include_str!("../../../../include/exercise/lib.rs");
+const MAIN_STUB: &str = include_str!("../../../../include/exercise/main.rs");
+
 impl CreateExercise {
-    pub fn create(self, common_args: &CommonArgs) -> Result<(), ModModError> {
+    pub fn create(self, common_args: &CommonArgs, tx: &mut Transaction) -> Result<(), ModModError> {
         let mut topic = modmod::load::TopicDef::load(&self.topic, None)
             .change_context(ModModError::default())?
             .data;
@@ -29,24 +42,21 @@ impl CreateExercise {
             .min(topic.exercises.len());
 
         let exercises_path = self.topic.parent().unwrap().join("exercises");
-        exercises_path.create_dir_all()?;
+        tx.create_dir_all(&exercises_path)?;
 
-        let exercise_crate_path = exercises_path
-            .canonicalize()
-            .unwrap()
-            .join(self.name.to_lowercase());
+        let crate_name = to_tag(&self.name);
+        let exercise_crate_path = exercises_path.canonicalize().unwrap().join(&crate_name);
         if common_args.force {
-            fs_extra::dir::remove(&exercise_crate_path)
-                .into_report()
-                .change_context(ModModError::default())?;
+            tx.remove_dir(&exercise_crate_path)?;
         }
+        tx.track_dir(&exercise_crate_path);
 
         let output = std::process::Command::new("cargo")
             .args([
                 OsStr::new("new"),
                 OsStr::new("--name"),
-                OsStr::new(&self.name),
-                OsStr::new("--bin"),
+                OsStr::new(&crate_name),
+                OsStr::new(if self.lib { "--lib" } else { "--bin" }),
                 exercise_crate_path.as_os_str(),
             ])
             .output()
@@ -69,12 +79,49 @@ impl CreateExercise {
             return Err(e);
         }
 
+        let (source_file, source_stub) = if self.lib {
+            ("lib.rs", LIB_STUB)
+        } else {
+            ("main.rs", MAIN_STUB)
+        };
+        exercise_crate_path
+            .join("src")
+            .join(source_file)
+            .create_file()?
+            .write_all(source_stub)?;
+        exercise_crate_path
+            .join("description.md")
+            .create_file()?
+            .write_all(DESCRIPTION_STUB)?;
+        exercise_crate_path.join("images").create_dir_all()?;
+
+        let template = self
+            .template
+            .clone()
+            .or_else(|| find_template(self.topic.parent().unwrap()));
+        if let Some(template) = &template {
+            println!("Using template {}", template.to_string_lossy());
+            let template = template
+                .canonicalize()
+                .into_report()
+                .attach_printable_lazy(|| {
+                    format!("Template folder {} not found", template.to_string_lossy())
+                })
+                .change_context(ModModError::default())?;
+            for file in template.get_dir_content()?.files {
+                let file = Path::new(&file);
+                let dest = exercise_crate_path.join(file.strip_prefix(&template).unwrap());
+                dest.parent().unwrap().create_dir_all()?;
+                file.copy(&dest)?;
+            }
+        }
+
         topic.exercises.insert(
             index,
             ExerciseDef {
                 name: self.name,
                 path: exercise_crate_path
-                    .strip_prefix(&self.topic.parent().unwrap().canonicalize().unwrap())
+                    .strip_prefix(self.topic.parent().unwrap().canonicalize().unwrap())
                     .unwrap()
                     .to_owned(),
                 ..Default::default()
@@ -83,10 +130,28 @@ impl CreateExercise {
 
         topic.exercises.dedup_by(|lhs, rhs| lhs.path == rhs.path);
 
-        self.topic
-            .create_file()?
+        tx.create_file(&self.topic)?
             .write_all(toml::to_string_pretty(&topic).unwrap())?;
 
-        Ok(())
+        validate_topic(&self.topic)
+    }
+}
+
+/// The folder in the content tree that holds the template for new exercises
+const TEMPLATE_DIR: &str = "templates/exercise";
+
+/// Find the nearest `templates/exercise` folder in `topic_dir` or one of its ancestors,
+/// up to the root of the git repository it's in
+fn find_template(topic_dir: &Path) -> Option<PathBuf> {
+    let topic_dir = topic_dir.canonicalize().ok()?;
+    for dir in topic_dir.ancestors() {
+        let template = dir.join(TEMPLATE_DIR);
+        if template.is_dir() {
+            return Some(template);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
     }
+    None
 }
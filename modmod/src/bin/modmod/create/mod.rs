@@ -1,7 +1,10 @@
+use std::path::Path;
+
 use clap::{Parser, Subcommand};
-use error_stack::Result;
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::load::{Load, ModuleDef, TopicDef};
 
-use crate::ModModError;
+use crate::{transaction::Transaction, ModModError};
 
 mod exercise;
 mod module;
@@ -31,23 +34,59 @@ enum What {
 }
 
 impl What {
-    fn create(self, common: &CommonArgs) -> Result<(), ModModError> {
+    fn create(self, common: &CommonArgs, tx: &mut Transaction) -> Result<(), ModModError> {
         match self {
-            What::Module(m) => m.create(common),
-            What::Unit(u) => u.create(common),
-            What::Topic(t) => t.create(common),
-            What::Exercise(e) => e.create(common),
+            What::Module(m) => m.create(common, tx),
+            What::Unit(u) => u.create(common, tx),
+            What::Topic(t) => t.create(common, tx),
+            What::Exercise(e) => e.create(common, tx),
         }
     }
 }
 
+/// Run the `create` subcommand, undoing all of its edits if they fail
+/// or leave the affected module unable to load
 pub fn run(args: Args) -> Result<(), ModModError> {
-    args.what.create(&args.common)
+    let mut tx = Transaction::default();
+    let result = args.what.create(&args.common, &mut tx);
+    tx.finish(result)
+}
+
+/// Check that the module at `mod_toml_path` still loads, including its topics and exercises
+fn validate_module(mod_toml_path: &Path) -> Result<(), ModModError> {
+    ModuleDef::load(mod_toml_path, None)
+        .change_context(ModModError::default())?
+        .resolve(1)
+        .change_context(ModModError::default())
+        .attach_printable("The module no longer loads")?;
+    Ok(())
+}
+
+/// Check that the topic at `topic_toml_path` still loads, along with the module containing it
+fn validate_topic(topic_toml_path: &Path) -> Result<(), ModModError> {
+    let topic_toml_path = topic_toml_path
+        .canonicalize()
+        .into_report()
+        .change_context(ModModError::default())?;
+    let module = topic_toml_path
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("mod.toml"))
+        .find(|mod_toml_path| mod_toml_path.is_file());
+    if let Some(mod_toml_path) = module {
+        return validate_module(&mod_toml_path);
+    }
+    TopicDef::load(&topic_toml_path, None)
+        .change_context(ModModError::default())?
+        .resolve(1)
+        .change_context(ModModError::default())
+        .attach_printable("The topic no longer loads")?;
+    Ok(())
 }
 
 mod imports {
-    pub(crate) use crate::create::CommonArgs;
     pub(crate) use crate::ModModError;
+    pub(crate) use crate::{create::CommonArgs, transaction::Transaction};
     pub use error_stack::{Result, ResultExt};
     pub use modmod::{
         io::{PathExt, WriteExt},
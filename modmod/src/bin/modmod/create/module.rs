@@ -1,6 +1,6 @@
 use modmod::load::ModuleDef;
 
-use super::imports::*;
+use super::{imports::*, validate_module};
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct CreateModule {
@@ -10,20 +10,21 @@ pub struct CreateModule {
 }
 
 impl CreateModule {
-    pub fn create(self, common_args: &CommonArgs) -> Result<(), ModModError> {
+    pub fn create(self, common_args: &CommonArgs, tx: &mut Transaction) -> Result<(), ModModError> {
         let mod_toml_path = self.path.join("mod.toml");
 
-        self.path.create_dir_all()?;
-        let mut mod_toml = mod_toml_path.try_create_file(common_args.force)?;
+        tx.create_dir_all(&self.path)?;
+        let mut mod_toml = tx.try_create_file(&mod_toml_path, common_args.force)?;
 
         let module = ModuleDef {
             name: self.name,
             description: self.description,
             units: vec![],
+            variables: Default::default(),
         };
 
         mod_toml.write_all(toml::to_string_pretty(&module).unwrap().as_bytes())?;
 
-        Ok(())
+        validate_module(&mod_toml_path)
     }
 }
@@ -1,6 +1,8 @@
+use std::path::Path;
+
 use modmod::load::{ModuleDef, TopicDef};
 
-use super::imports::*;
+use super::{imports::*, validate_module};
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct CreateTopic {
@@ -17,7 +19,7 @@ pub struct CreateTopic {
 }
 
 impl CreateTopic {
-    pub fn create(self, common_args: &CommonArgs) -> Result<(), ModModError> {
+    pub fn create(self, common_args: &CommonArgs, tx: &mut Transaction) -> Result<(), ModModError> {
         let mut module = ModuleDef::load(&self.module, None)
             .change_context(ModModError::default())?
             .data;
@@ -36,28 +38,25 @@ impl CreateTopic {
             )));
         };
 
-        let topic_toml_path = self.module.parent().unwrap().join("topics").join(&self.dir);
-        topic_toml_path.create_dir_all()?;
-        let mut topic_toml = topic_toml_path
-            .join("topic.toml")
-            .try_create_file(common_args.force)?;
+        let topic_dir = Path::new("topics").join(&self.dir);
+        let topic_toml_path = self.module.parent().unwrap().join(&topic_dir);
+        tx.create_dir_all(&topic_toml_path)?;
+        let mut topic_toml =
+            tx.try_create_file(topic_toml_path.join("topic.toml"), common_args.force)?;
 
         let topic = TopicDef {
             name: self.name,
             ..Default::default()
         };
-        unit.topics.push(self.dir.join("mod.toml"));
+        unit.topics.push(topic_dir.join("topic.toml"));
         unit.topics.dedup();
 
         topic_toml.write_all(toml::to_string_pretty(&topic).unwrap().as_bytes())?;
-        topic_toml_path
-            .join("slides.md")
-            .try_create_file(common_args.force)?;
+        tx.try_create_file(topic_toml_path.join("slides.md"), common_args.force)?;
 
-        self.module
-            .create_file()?
+        tx.create_file(&self.module)?
             .write_all(toml::to_string_pretty(&module).unwrap())?;
 
-        Ok(())
+        validate_module(&self.module)
     }
 }
@@ -1,6 +1,6 @@
 use modmod::load::{ModuleDef, UnitDef};
 
-use super::imports::*;
+use super::{imports::*, validate_module};
 
 #[derive(Debug, Clone, clap::Args)]
 pub struct CreateUnit {
@@ -11,7 +11,11 @@ pub struct CreateUnit {
 }
 
 impl CreateUnit {
-    pub fn create(self, _common_args: &CommonArgs) -> Result<(), ModModError> {
+    pub fn create(
+        self,
+        _common_args: &CommonArgs,
+        tx: &mut Transaction,
+    ) -> Result<(), ModModError> {
         let mut module = ModuleDef::load(&self.module, None)
             .change_context(ModModError::default())?
             .data;
@@ -30,9 +34,9 @@ impl CreateUnit {
             },
         );
 
-        self.module
-            .create_file()?
+        tx.create_file(&self.module)?
             .write_all(toml::to_string_pretty(&module).unwrap().as_bytes())?;
-        Ok(())
+
+        validate_module(&self.module)
     }
 }
@@ -2,7 +2,13 @@ use std::{fs, path::PathBuf};
 
 use clap::Parser;
 use error_stack::{IntoReport, Result, ResultExt};
-use modmod::{patch::GenPatchOptions, SlidesRenderOptions, TrackRenderOptions};
+use modmod::{
+    git::{GitRelease, GitReleaseOptions},
+    patch::GenPatchOptions,
+    slug::{to_tag, SlugCollisionPolicy},
+    vendor::VendorOptions,
+    SlideImageLayout, SlidesRenderOptions, TrackRenderOptions,
+};
 
 use crate::ModModError;
 
@@ -40,6 +46,48 @@ pub struct Args {
         help = "The path of the package.json stub to use when generating the slide package"
     )]
     package_json: Option<PathBuf>,
+    #[arg(
+        long = "flat-slide-images",
+        help = "Copy the images of all topics into a single slides/images folder, instead of one folder per topic"
+    )]
+    flat_slide_images: bool,
+    #[arg(
+        long = "fail-on-slug-collision",
+        help = "Fail if two items would be written to the same path, instead of adding a numeric suffix to the latter"
+    )]
+    fail_on_slug_collision: bool,
+    #[arg(
+        long = "archives",
+        help = "Package the exercises of each module and of the whole track into .tar.gz and .zip archives"
+    )]
+    exercise_archives: bool,
+    #[arg(
+        long = "vendor",
+        help = "Vendor the dependencies of all exercises into exercises/vendor, so that they can be built offline"
+    )]
+    vendor: bool,
+    #[arg(
+        long = "vendor-offline",
+        help = "Only vendor crates from the local cargo cache or a configured registry mirror",
+        requires = "vendor"
+    )]
+    vendor_offline: bool,
+    #[arg(
+        long = "git",
+        help = "Commit the rendered exercises to a release branch in the local git repository at given path"
+    )]
+    git_repo: Option<PathBuf>,
+    #[arg(
+        long = "git-branch",
+        help = "The branch to commit the rendered exercises to",
+        default_value = "release"
+    )]
+    git_branch: String,
+    #[arg(
+        long = "release-version",
+        help = "The version of the release, used to tag the commit of the rendered exercises [default: the version of the track]"
+    )]
+    release_version: Option<String>,
 }
 
 pub fn run(args: Args) -> Result<(), ModModError> {
@@ -51,12 +99,25 @@ pub fn run(args: Args) -> Result<(), ModModError> {
         slide_theme,
         package_json,
         patch_file,
+        flat_slide_images,
+        fail_on_slug_collision,
+        exercise_archives,
+        vendor,
+        vendor_offline,
+        git_repo,
+        git_branch,
+        release_version,
     } = args;
 
     let slide_opts = SlidesRenderOptions {
         theme: &slide_theme,
         package_json,
         url_base: slide_url_base.as_str(),
+        image_layout: if flat_slide_images {
+            SlideImageLayout::Flat
+        } else {
+            SlideImageLayout::PerTopic
+        },
     };
 
     let (out_dir, patch_opts) = if let Some(patch_file) = patch_file {
@@ -72,17 +133,46 @@ pub fn run(args: Args) -> Result<(), ModModError> {
     };
 
     let track_opts = TrackRenderOptions {
-        out_dir,
+        out_dir: out_dir.clone(),
         slide_opts,
         clear_output_dir,
+        slug_collisions: if fail_on_slug_collision {
+            SlugCollisionPolicy::Fail
+        } else {
+            SlugCollisionPolicy::Disambiguate
+        },
+        exercise_archives,
+        vendor: vendor.then_some(VendorOptions {
+            offline: vendor_offline,
+        }),
+        hooks: vec![],
     };
 
     let track =
         modmod::Track::load_toml_def(track_toml_path).change_context(ModModError::default())?;
+    let release_version = release_version.or_else(|| track.metadata.version.clone());
+    if git_repo.is_some() && release_version.is_none() {
+        return Err(ModModError::report().attach_printable(
+            "Publishing to git requires a version. Pass --release-version or set `version` in the track.",
+        ));
+    }
     track
         .render(track_opts)
         .change_context(ModModError::default())?;
 
+    if let (Some(git_repo), Some(release_version)) = (git_repo, release_version) {
+        let tag = format!("{}-{release_version}", to_tag(&track.name));
+        let message = format!("Release {} {release_version}", track.name);
+        GitRelease::render(GitReleaseOptions {
+            exercises_dir: out_dir.join("exercises"),
+            repo: git_repo,
+            branch: &git_branch,
+            tag: &tag,
+            message: &message,
+        })
+        .change_context(ModModError::default())?;
+    }
+
     if let Some(patch_opts) = patch_opts {
         let tmp_dir = patch_opts.new_dir.clone();
         modmod::patch::Patch::render(patch_opts).change_context(ModModError::default())?;
@@ -0,0 +1,89 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    grade::{GradeOptions, GradeReport},
+    io::{PathExt, WriteExt},
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(short = 't', long = "track", help = "The track the student follows")]
+    track_toml_path: PathBuf,
+    #[arg(help = "The student's exercises folder")]
+    student_dir: PathBuf,
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The folder the grades.json and junit.xml reports will be written to",
+        default_value = "."
+    )]
+    out_dir: PathBuf,
+    #[arg(
+        long = "work-dir",
+        help = "The folder to build the submissions in [default: <OUT_DIR>/work]"
+    )]
+    work_dir: Option<PathBuf>,
+    #[arg(
+        short = 'e',
+        long = "exercise",
+        help = "Grade the exercise with this reference, like 2.3.1, or name. Defaults to all exercises with hidden tests"
+    )]
+    exercises: Vec<String>,
+    #[arg(
+        long = "timeout",
+        help = "Maximum number of seconds the tests of a single exercise may take",
+        default_value_t = 120
+    )]
+    timeout: u64,
+}
+
+pub fn run(args: Args) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        student_dir,
+        out_dir,
+        work_dir,
+        exercises,
+        timeout,
+    } = args;
+
+    let track =
+        modmod::Track::load_toml_def(track_toml_path).change_context(ModModError::default())?;
+    let report = GradeReport::create(
+        &track,
+        GradeOptions {
+            student_dir,
+            work_dir: work_dir.unwrap_or_else(|| out_dir.join("work")),
+            exercises: &exercises,
+            timeout: Duration::from_secs(timeout),
+            on_grade: Some(&|reference| println!("Grading exercise {reference}")),
+        },
+    )
+    .change_context(ModModError::default())?;
+
+    for exercise in report.exercises.iter() {
+        println!(
+            "{} {}: {} ({:.0}%)",
+            exercise.reference,
+            exercise.name,
+            exercise.status,
+            exercise.score * 100.
+        );
+    }
+
+    out_dir.create_dir_all()?;
+    out_dir
+        .join("grades.json")
+        .create_file()?
+        .write_all(report.to_json())?;
+    out_dir
+        .join("junit.xml")
+        .create_file()?
+        .write_all(report.to_junit_xml())?;
+
+    Ok(())
+}
@@ -2,8 +2,13 @@ use clap::{Parser, Subcommand};
 use error_stack::Report;
 use std::{fmt, process::exit};
 
+mod check_snippets;
 mod create;
 mod gen;
+mod grade;
+mod progress;
+mod relocate;
+mod transaction;
 
 #[non_exhaustive]
 #[derive(Debug, Default)]
@@ -33,6 +38,12 @@ struct App {
 enum Command {
     Generate(gen::Args),
     Create(create::Args),
+    Progress(progress::Args),
+    Grade(grade::Args),
+    /// Move topics, units and exercises, updating all references to them
+    Move(relocate::Args),
+    /// Compile-check the Rust code blocks in slides and exercise descriptions
+    CheckSnippets(check_snippets::Args),
 }
 
 fn main() {
@@ -51,6 +62,35 @@ fn main() {
                 exit(1);
             }
         }
+        Command::Progress(args) => {
+            if let Err(e) = progress::run(args) {
+                eprintln!("Error creating progress report: {e:?}");
+                exit(1);
+            }
+        }
+        Command::Grade(args) => {
+            if let Err(e) = grade::run(args) {
+                eprintln!("Error grading exercises: {e:?}");
+                exit(1);
+            }
+        }
+        Command::Move(args) => {
+            let dry_run = args.dry_run;
+            if let Err(e) = relocate::run(args) {
+                eprintln!("Error moving content: {e:?}");
+                exit(1);
+            }
+            if dry_run {
+                println!("Dry run, nothing written");
+                return;
+            }
+        }
+        Command::CheckSnippets(args) => {
+            if let Err(e) = check_snippets::run(args) {
+                eprintln!("Error checking snippets: {e:?}");
+                exit(1);
+            }
+        }
     }
 
     println!("Done!");
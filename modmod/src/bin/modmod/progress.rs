@@ -0,0 +1,76 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt};
+use modmod::{
+    io::{PathExt, WriteExt},
+    progress::{ProgressReport, ProgressReportOptions},
+    TestRunOptions,
+};
+
+use crate::ModModError;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(short = 't', long = "track", help = "The track the student follows")]
+    track_toml_path: PathBuf,
+    #[arg(help = "The student's exercises folder")]
+    student_dir: PathBuf,
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "The folder the progress.json and progress.md reports will be written to",
+        default_value = "."
+    )]
+    out_dir: PathBuf,
+    #[arg(long = "no-tests", help = "Don't run the tests of the exercises")]
+    no_tests: bool,
+    #[arg(
+        long = "timeout",
+        help = "Maximum number of seconds the tests of a single exercise may take",
+        default_value_t = 120
+    )]
+    timeout: u64,
+    #[arg(long = "offline", help = "Run cargo without accessing the network")]
+    offline: bool,
+}
+
+pub fn run(args: Args) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        student_dir,
+        out_dir,
+        no_tests,
+        timeout,
+        offline,
+    } = args;
+
+    let track =
+        modmod::Track::load_toml_def(track_toml_path).change_context(ModModError::default())?;
+    let report = ProgressReport::create(
+        &track,
+        ProgressReportOptions {
+            student_dir,
+            run_tests: !no_tests,
+            test_opts: TestRunOptions {
+                timeout: Duration::from_secs(timeout),
+                offline,
+                target_dir: None,
+            },
+            on_test_run: Some(&|reference| println!("Running tests of exercise {reference}")),
+        },
+    )
+    .change_context(ModModError::default())?;
+
+    out_dir.create_dir_all()?;
+    out_dir
+        .join("progress.json")
+        .create_file()?
+        .write_all(report.to_json())?;
+    out_dir
+        .join("progress.md")
+        .create_file()?
+        .write_all(report.to_markdown())?;
+
+    Ok(())
+}
@@ -0,0 +1,857 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{Parser, Subcommand};
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::{
+    io::{PathExt, WriteExt},
+    load::{Load, ModuleDef, PathTo, TopicDef, TrackDef},
+    number_exercises,
+};
+use toml_edit::{Array, ArrayOfTables, Document, Item, Table};
+
+use crate::{transaction::Transaction, ModModError};
+
+#[derive(Parser)]
+pub struct Args {
+    track_toml_path: PathBuf,
+    #[arg(
+        long = "dry-run",
+        help = "Show how the numbering changes, without writing anything"
+    )]
+    pub dry_run: bool,
+    #[command(subcommand)]
+    what: What,
+}
+
+#[derive(Subcommand)]
+enum What {
+    /// Move a topic to another unit, possibly in another module
+    Topic {
+        #[arg(help = "The topic.toml of the topic")]
+        topic: PathBuf,
+        #[arg(help = "The unit to move the topic to, like `2.4`")]
+        unit: UnitRef,
+        #[arg(
+            long = "from",
+            help = "The unit to move the topic out of, if it's included in more than one unit"
+        )]
+        from: Option<UnitRef>,
+        #[arg(
+            short = 'p',
+            long = "position",
+            help = "The position of the topic in the unit, starting at 1. Defaults to the last one"
+        )]
+        position: Option<usize>,
+    },
+    /// Move a unit to another position within its module
+    Unit {
+        #[arg(help = "The unit to move, like `2.4`")]
+        unit: UnitRef,
+        #[arg(help = "The new position of the unit in its module, starting at 1")]
+        position: usize,
+    },
+    /// Move an exercise to another topic. The exercise crate is moved into the `exercises`
+    /// folder of the topic if it's in the folder of its current topic.
+    Exercise {
+        #[arg(help = "The folder of the exercise crate")]
+        exercise: PathBuf,
+        #[arg(help = "The topic.toml of the topic to move the exercise to")]
+        topic: PathBuf,
+        #[arg(
+            short = 'p',
+            long = "position",
+            help = "The position of the exercise in the topic, starting at 1. Defaults to the last one"
+        )]
+        position: Option<usize>,
+    },
+}
+
+/// A unit, numbered like in the rendered track
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct UnitRef {
+    module: usize,
+    unit: usize,
+}
+
+impl FromStr for UnitRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let parse = |n: &str| n.trim().parse().ok().filter(|n| *n > 0);
+        s.split_once('.')
+            .and_then(|(module, unit)| {
+                Some(UnitRef {
+                    module: parse(module)?,
+                    unit: parse(unit)?,
+                })
+            })
+            .ok_or_else(|| format!("`{s}` is not a unit number like `2.4`"))
+    }
+}
+
+impl fmt::Display for UnitRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.module, self.unit)
+    }
+}
+
+/// A unit, topic or exercise, with its number in the rendered track
+#[derive(Debug)]
+struct Numbered {
+    /// Identifies the item before and after a move
+    key: String,
+    /// The key of the unit the item is in
+    unit_key: String,
+    kind: &'static str,
+    name: String,
+    number: String,
+}
+
+/// The modules and topics of a track, as they're being edited
+struct Outline {
+    modules: Vec<PathTo<ModuleDef>>,
+    /// The topics of all modules, by the canonical path of their `topic.toml`
+    topics: BTreeMap<PathBuf, TopicDef>,
+    /// The edited `mod.toml` and `topic.toml` files. Edits are made to these documents
+    /// as well as to the definitions, to keep the formatting and comments of the files.
+    documents: BTreeMap<PathBuf, Document>,
+    /// Directories to move, from where to where
+    moved_dirs: Vec<(PathBuf, PathBuf)>,
+}
+
+pub fn run(args: Args) -> Result<(), ModModError> {
+    let Args {
+        track_toml_path,
+        dry_run,
+        what,
+    } = args;
+
+    let mut outline = Outline::load(&track_toml_path)?;
+    let before = outline.numbering();
+    match what {
+        What::Topic {
+            topic,
+            unit,
+            from,
+            position,
+        } => outline.move_topic(&topic, from, unit, position)?,
+        What::Unit { unit, position } => outline.move_unit(unit, position)?,
+        What::Exercise {
+            exercise,
+            topic,
+            position,
+        } => outline.move_exercise(&exercise, &topic, position)?,
+    }
+
+    let changes = numbering_changes(&before, &outline.numbering());
+    if changes.is_empty() {
+        println!("The numbering doesn't change");
+    }
+    for change in changes {
+        println!("{change}");
+    }
+    for (from, to) in outline.moved_dirs.iter() {
+        println!(
+            "Moving {} to {}",
+            from.to_string_lossy(),
+            to.to_string_lossy()
+        );
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    let mut tx = Transaction::default();
+    let result = outline.save(&mut tx).and_then(|()| {
+        modmod::Track::load_toml_def(&track_toml_path)
+            .change_context(ModModError::default())
+            .attach_printable("The track no longer loads")?;
+        Ok(())
+    });
+    tx.finish(result)
+}
+
+impl Outline {
+    fn load(track_toml_path: &Path) -> Result<Self, ModModError> {
+        let track = TrackDef::load(track_toml_path, None).change_context(ModModError::default())?;
+        let track_dir = track.path.parent().unwrap();
+
+        let mut modules = vec![];
+        let mut topics = BTreeMap::new();
+        for module_path in track.data.modules.iter() {
+            let module = ModuleDef::load(module_path, Some(track_dir))
+                .change_context(ModModError::default())?;
+            let module_dir = module.path.parent().unwrap();
+            for topic_path in module.data.units.iter().flat_map(|u| u.topics.iter()) {
+                let topic = TopicDef::load(topic_path, Some(module_dir))
+                    .change_context(ModModError::default())?;
+                topics.insert(topic.path, topic.data);
+            }
+            modules.push(module);
+        }
+
+        Ok(Self {
+            modules,
+            topics,
+            documents: BTreeMap::new(),
+            moved_dirs: vec![],
+        })
+    }
+
+    /// Number every unit, topic and exercise. Exercises are numbered within their unit.
+    fn numbering(&self) -> Vec<Numbered> {
+        let mut numbered = vec![];
+        for (module, m) in self.modules.iter().zip(1..) {
+            let module_dir = module.path.parent().unwrap();
+            for (unit, u) in module.data.units.iter().zip(1..) {
+                let unit_key = format!("{}#{}", module.path.to_string_lossy(), unit.name);
+                numbered.push(Numbered {
+                    key: unit_key.clone(),
+                    unit_key: unit_key.clone(),
+                    kind: "Unit",
+                    name: unit.name.clone(),
+                    number: format!("{m}.{u}"),
+                });
+
+                let topics: Vec<_> = unit
+                    .topics
+                    .iter()
+                    .map(|topic_path| canonical(module_dir.join(topic_path)))
+                    .filter_map(|topic_path| {
+                        let topic = self.topics.get(&topic_path)?;
+                        Some((topic_path, topic))
+                    })
+                    .collect();
+                let mut numbered_exercises =
+                    number_exercises(topics.iter().map(|(_, topic)| topic.exercises.as_slice()));
+                for (topic_path, topic) in topics.iter() {
+                    numbered.push(Numbered {
+                        key: topic_path.to_string_lossy().into_owned(),
+                        unit_key: unit_key.clone(),
+                        kind: "Topic",
+                        name: topic.name.clone(),
+                        number: format!("{m}.{u}"),
+                    });
+                    let topic_dir = topic_path.parent().unwrap();
+                    let topic_exercises = numbered_exercises.by_ref().take(topic.exercises.len());
+                    for (exercise, n) in topic_exercises {
+                        numbered.push(Numbered {
+                            key: canonical(topic_dir.join(&exercise.path))
+                                .to_string_lossy()
+                                .into_owned(),
+                            unit_key: unit_key.clone(),
+                            kind: "Exercise",
+                            name: exercise.name.clone(),
+                            number: format!("{m}.{u}.{n}"),
+                        });
+                    }
+                }
+            }
+        }
+
+        // Items that are included more than once are told apart by their order
+        let mut occurrences = BTreeMap::new();
+        for item in numbered.iter_mut() {
+            let count = occurrences.entry(item.key.clone()).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                item.key += &format!("#{count}");
+            }
+        }
+        numbered
+    }
+
+    fn move_topic(
+        &mut self,
+        topic: &Path,
+        from: Option<UnitRef>,
+        to: UnitRef,
+        position: Option<usize>,
+    ) -> Result<(), ModModError> {
+        let topic_path = canonical_existing(topic)?;
+        if !self.topics.contains_key(&topic_path) {
+            return Err(ModModError::report().attach_printable(format!(
+                "Topic {} is not part of the track",
+                topic.to_string_lossy()
+            )));
+        }
+        // Check the units before editing anything
+        self.unit_mut(to)?;
+        if let Some(from) = from {
+            self.unit_mut(from)?;
+        }
+
+        // A topic can be included in several units, so only move it out of one of them
+        let mut occurrences = vec![];
+        for (module, m) in self.modules.iter().zip(1..) {
+            let module_dir = module.path.parent().unwrap();
+            for (unit, u) in module.data.units.iter().zip(1..) {
+                let index = unit
+                    .topics
+                    .iter()
+                    .position(|t| canonical(module_dir.join(t)) == topic_path);
+                if let Some(index) = index {
+                    occurrences.push((UnitRef { module: m, unit: u }, index));
+                }
+            }
+        }
+        let (from, index) = match (from, occurrences.as_slice()) {
+            (Some(from), _) => occurrences
+                .iter()
+                .find(|(unit, _)| *unit == from)
+                .copied()
+                .ok_or_else(|| {
+                    ModModError::report().attach_printable(format!(
+                        "Topic {} is not in unit {from}",
+                        topic.to_string_lossy()
+                    ))
+                })?,
+            (None, [occurrence]) => *occurrence,
+            (None, _) => {
+                let units: Vec<_> = occurrences.iter().map(|(u, _)| u.to_string()).collect();
+                return Err(ModModError::report().attach_printable(format!(
+                    "Topic {} is included in units {}. Pass --from to choose which one to move it out of",
+                    topic.to_string_lossy(),
+                    units.join(", ")
+                )));
+            }
+        };
+
+        let module_path = self.modules[from.module - 1].path.clone();
+        let topics = topics_array(self.document(&module_path)?, from.unit)?;
+        remove_value(topics, index);
+        self.modules[from.module - 1].data.units[from.unit - 1]
+            .topics
+            .remove(index);
+
+        let module = &self.modules[to.module - 1];
+        let module_path = module.path.clone();
+        let relative_topic_path = relative_path(module.path.parent().unwrap(), &topic_path);
+        let index = position_index(position, module.data.units[to.unit - 1].topics.len());
+        let topics = topics_array(self.document(&module_path)?, to.unit)?;
+        insert_string(topics, index, &relative_topic_path.to_string_lossy());
+        self.modules[to.module - 1].data.units[to.unit - 1]
+            .topics
+            .insert(index, relative_topic_path);
+        Ok(())
+    }
+
+    fn move_unit(&mut self, unit: UnitRef, position: usize) -> Result<(), ModModError> {
+        self.unit_mut(unit)?;
+        let module_path = self.modules[unit.module - 1].path.clone();
+        let units = &mut self.modules[unit.module - 1].data.units;
+        let moved = units.remove(unit.unit - 1);
+        let index = position_index(Some(position), units.len());
+        units.insert(index, moved);
+
+        let units = tables(self.document(&module_path)?, "units")?;
+        let mut reordered: Vec<_> = units.iter().cloned().collect();
+        let moved = reordered.remove(unit.unit - 1);
+        reordered.insert(index, moved);
+        set_tables(units, reordered);
+        Ok(())
+    }
+
+    fn move_exercise(
+        &mut self,
+        exercise: &Path,
+        topic: &Path,
+        position: Option<usize>,
+    ) -> Result<(), ModModError> {
+        let exercise_path = canonical_existing(exercise)?;
+        let target_path = canonical_existing(topic)?;
+        if !self.topics.contains_key(&target_path) {
+            return Err(ModModError::report().attach_printable(format!(
+                "Topic {} is not part of the track",
+                topic.to_string_lossy()
+            )));
+        }
+
+        let source = self.topics.iter().find_map(|(topic_path, topic)| {
+            let topic_dir = topic_path.parent().unwrap();
+            let index = topic
+                .exercises
+                .iter()
+                .position(|e| canonical(topic_dir.join(&e.path)) == exercise_path)?;
+            Some((topic_path.clone(), index))
+        });
+        let Some((source_path, index)) = source else {
+            return Err(ModModError::report().attach_printable(format!(
+                "Exercise {} is not part of any topic of the track",
+                exercise.to_string_lossy()
+            )));
+        };
+
+        let exercises = tables(self.document(&source_path)?, "exercises")?;
+        let mut moved_table = exercises.get(index).unwrap().clone();
+        exercises.remove(index);
+        let mut moved = self
+            .topics
+            .get_mut(&source_path)
+            .unwrap()
+            .exercises
+            .remove(index);
+
+        // Crates that live in the folder of their topic move along with the exercise
+        let source_dir = source_path.parent().unwrap();
+        let target_dir = target_path.parent().unwrap();
+        let mut new_exercise_path = exercise_path.clone();
+        if exercise_path.starts_with(source_dir) && source_dir != target_dir {
+            new_exercise_path = target_dir
+                .join("exercises")
+                .join(exercise_path.file_name().unwrap());
+            self.check_exercise_dir_movable(&exercise_path, &new_exercise_path)?;
+            self.moved_dirs
+                .push((exercise_path.clone(), new_exercise_path.clone()));
+        }
+
+        moved.path = relative_path(target_dir, &new_exercise_path);
+        let exercises = &mut self.topics.get_mut(&target_path).unwrap().exercises;
+        let index = position_index(position, exercises.len());
+        set_string(&mut moved_table, "path", &moved.path.to_string_lossy());
+        exercises.insert(index, moved);
+
+        let document = self.document(&target_path)?;
+        if !document.contains_key("exercises") {
+            document.insert("exercises", Item::ArrayOfTables(ArrayOfTables::new()));
+        }
+        let exercises = tables(document, "exercises")?;
+        let mut tables: Vec<_> = exercises.iter().cloned().collect();
+        // The table comes from another document, so let it take the position of its predecessor
+        tables.insert(index, detached(&moved_table));
+        set_tables(exercises, tables);
+        Ok(())
+    }
+
+    /// Check that the folder of an exercise crate can be moved without breaking references
+    /// to it, or path dependencies of the crate itself
+    fn check_exercise_dir_movable(&self, from: &Path, to: &Path) -> Result<(), ModModError> {
+        if to.exists() {
+            return Err(ModModError::report().attach_printable(format!(
+                "Unable to move the exercise crate to {}, as it already exists",
+                to.to_string_lossy()
+            )));
+        }
+
+        let mut references = 0;
+        for (topic_path, topic) in self.topics.iter() {
+            let topic_dir = topic_path.parent().unwrap();
+            for exercise in topic.exercises.iter() {
+                let exercise_dir = canonical(topic_dir.join(&exercise.path));
+                if exercise_dir == from {
+                    references += 1;
+                    continue;
+                }
+                for dependency in path_dependencies(&exercise_dir)? {
+                    if canonical(exercise_dir.join(dependency)).starts_with(from) {
+                        return Err(ModModError::report().attach_printable(format!(
+                            "Exercise {} depends on the exercise crate, so it can't be moved",
+                            exercise_dir.to_string_lossy()
+                        )));
+                    }
+                }
+            }
+        }
+        if references > 1 {
+            return Err(ModModError::report().attach_printable(
+                "The exercise crate is used by more than one exercise, so it can't be moved",
+            ));
+        }
+
+        for dependency in path_dependencies(from)? {
+            if !canonical(from.join(&dependency)).starts_with(from) {
+                return Err(ModModError::report().attach_printable(format!(
+                    "The exercise crate has a path dependency on {}, which would break if it's moved",
+                    dependency.to_string_lossy()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn unit_mut(&mut self, unit: UnitRef) -> Result<&mut modmod::load::UnitDef, ModModError> {
+        self.modules
+            .get_mut(unit.module - 1)
+            .and_then(|m| m.data.units.get_mut(unit.unit - 1))
+            .ok_or_else(|| {
+                ModModError::report().attach_printable(format!("There is no unit {unit}"))
+            })
+    }
+
+    /// The document of a `mod.toml` or `topic.toml` file, to edit it
+    fn document(&mut self, path: &Path) -> Result<&mut Document, ModModError> {
+        if !self.documents.contains_key(path) {
+            let document = path
+                .read_to_string()?
+                .parse::<Document>()
+                .into_report()
+                .attach_printable_lazy(|| format!("Unable to parse {}", path.to_string_lossy()))
+                .change_context(ModModError::default())?;
+            self.documents.insert(path.to_path_buf(), document);
+        }
+        Ok(self.documents.get_mut(path).unwrap())
+    }
+
+    /// Move the exercise crates, and write the edited `mod.toml` and `topic.toml` files
+    fn save(&self, tx: &mut Transaction) -> Result<(), ModModError> {
+        for (from, to) in self.moved_dirs.iter() {
+            tx.move_dir(from, to)?;
+        }
+        for (path, document) in self.documents.iter() {
+            tx.create_file(path)?.write_all(document.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// The `[[key]]` tables of a document
+fn tables<'d>(document: &'d mut Document, key: &str) -> Result<&'d mut ArrayOfTables, ModModError> {
+    document
+        .get_mut(key)
+        .and_then(Item::as_array_of_tables_mut)
+        .ok_or_else(|| {
+            ModModError::report().attach_printable(format!(
+                "Expected `{key}` to be written as [[{key}]] tables"
+            ))
+        })
+}
+
+/// The `topics` array of unit `unit`, starting at 1, in the document of a `mod.toml`
+fn topics_array(document: &mut Document, unit: usize) -> Result<&mut Array, ModModError> {
+    tables(document, "units")?
+        .get_mut(unit - 1)
+        .and_then(|unit| unit.get_mut("topics"))
+        .and_then(Item::as_array_mut)
+        .ok_or_else(|| {
+            ModModError::report()
+                .attach_printable(format!("Expected unit {unit} to list its topics"))
+        })
+}
+
+/// Replace the tables in `array`, keeping them at the positions in the document
+/// the original tables had. Tables without a position follow their predecessor.
+fn set_tables(array: &mut ArrayOfTables, tables: Vec<Table>) {
+    let mut positions: Vec<_> = array.iter().filter_map(Table::position).collect();
+    positions.sort();
+    let mut positions = positions.into_iter();
+    array.clear();
+    for mut table in tables {
+        if table.position().is_some() {
+            table.set_position(positions.next().unwrap());
+        }
+        array.push(table);
+    }
+}
+
+/// A copy of `table` without a position in a document
+fn detached(table: &Table) -> Table {
+    let mut detached = Table::new();
+    for (key, item) in table.iter() {
+        detached.insert(key, item.clone());
+    }
+    *detached.decor_mut() = table.decor().clone();
+    detached
+}
+
+/// Set `key` in `table` to a string, keeping the formatting around the value
+fn set_string(table: &mut Table, key: &str, value: &str) {
+    match table.get_mut(key).and_then(Item::as_value_mut) {
+        Some(old) => {
+            let decor = old.decor().clone();
+            *old = value.into();
+            *old.decor_mut() = decor;
+        }
+        None => {
+            table.insert(key, toml_edit::value(value));
+        }
+    }
+}
+
+/// The whitespace and comments before a value, or `""` if there's none
+fn prefix(value: &toml_edit::Value) -> &str {
+    value
+        .decor()
+        .prefix()
+        .and_then(|p| p.as_str())
+        .unwrap_or("")
+}
+
+/// Whether the values of `array` are on lines of their own
+fn is_multiline(array: &Array) -> bool {
+    array.iter().any(|v| prefix(v).contains('\n'))
+}
+
+/// Remove a value from `array`, along with the comments on its line
+fn remove_value(array: &mut Array, index: usize) {
+    let removed = array.remove(index);
+    if !is_multiline(array) {
+        array.fmt();
+        return;
+    }
+    // The comment after a value is in the prefix of the next value, or in the trailing
+    // whitespace of the array. Replace that line with the line before the removed value.
+    let before = match prefix(&removed).split_once('\n') {
+        Some((before, _)) => format!("{before}\n"),
+        None => return,
+    };
+    let join = |after: &str| {
+        after
+            .split_once('\n')
+            .map(|(_, rest)| format!("{before}{rest}"))
+    };
+    match array.get_mut(index) {
+        Some(next) => {
+            if let Some(joined) = join(prefix(next)) {
+                next.decor_mut().set_prefix(joined);
+            }
+        }
+        None => {
+            let trailing = array.trailing().as_str().unwrap_or("").to_string();
+            if let Some(joined) = join(&trailing) {
+                array.set_trailing(joined);
+            }
+        }
+    }
+}
+
+/// Insert a string into `array`, formatted like the other values
+fn insert_string(array: &mut Array, index: usize, value: &str) {
+    if !is_multiline(array) {
+        array.insert(index, value);
+        array.fmt();
+        return;
+    }
+    // Indent the value like the first one. The comment at the end of the line before
+    // the value stays on that line.
+    let first = prefix(array.get(0).unwrap());
+    let indent = first.rsplit('\n').next().unwrap_or_default().to_string();
+    let gap = match array.get(index) {
+        Some(next) => prefix(next).to_string(),
+        None => array.trailing().as_str().unwrap_or("").to_string(),
+    };
+    let (line_end, rest) = gap.split_once('\n').unwrap_or(("", &gap));
+    let value = toml_edit::Value::from(value).decorated(format!("{line_end}\n{indent}"), "");
+    let gap = format!("\n{rest}");
+    match array.get_mut(index) {
+        Some(next) => next.decor_mut().set_prefix(gap),
+        None => array.set_trailing(gap),
+    }
+    array.insert_formatted(index, value);
+}
+
+/// Describe the items whose number changed, like `Exercise 2.3.1 -> 2.4.1: Ring buffer`.
+/// Topics are only listed if they moved to another unit.
+fn numbering_changes(before: &[Numbered], after: &[Numbered]) -> Vec<String> {
+    let before: BTreeMap<_, _> = before.iter().map(|n| (&n.key, n)).collect();
+    after
+        .iter()
+        .filter_map(|n| {
+            let old = before.get(&n.key)?;
+            let moved = match n.kind {
+                "Topic" => old.unit_key != n.unit_key,
+                _ => old.number != n.number,
+            };
+            moved.then(|| format!("{} {} -> {}: {}", n.kind, old.number, n.number, n.name))
+        })
+        .collect()
+}
+
+/// The relative paths of the path dependencies in the `Cargo.toml` of a crate, if it has one
+fn path_dependencies(crate_dir: &Path) -> Result<Vec<PathBuf>, ModModError> {
+    const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+    let manifest_path = crate_dir.join("Cargo.toml");
+    if !manifest_path.exists() {
+        return Ok(vec![]);
+    }
+    let manifest: toml::Table = manifest_path
+        .read_to_string()?
+        .parse()
+        .into_report()
+        .attach_printable_lazy(|| format!("Unable to parse {}", manifest_path.to_string_lossy()))
+        .change_context(ModModError::default())?;
+
+    let targets = manifest
+        .get("target")
+        .and_then(|t| t.as_table())
+        .into_iter()
+        .flat_map(|targets| targets.values().filter_map(|t| t.as_table()));
+    let paths = std::iter::once(&manifest)
+        .chain(targets)
+        .flat_map(|table| DEPENDENCY_TABLES.iter().filter_map(|key| table.get(*key)))
+        .filter_map(|dependencies| dependencies.as_table())
+        .flat_map(|dependencies| dependencies.values())
+        .filter_map(|dependency| dependency.get("path")?.as_str())
+        .map(PathBuf::from)
+        .filter(|path| path.is_relative())
+        .collect();
+    Ok(paths)
+}
+
+/// The index to insert an item at, given its position starting at 1
+fn position_index(position: Option<usize>, len: usize) -> usize {
+    position.map_or(len, |p| p.saturating_sub(1).min(len))
+}
+
+fn canonical(path: PathBuf) -> PathBuf {
+    path.canonicalize().unwrap_or(path)
+}
+
+fn canonical_existing(path: &Path) -> Result<PathBuf, ModModError> {
+    path.canonicalize()
+        .into_report()
+        .attach_printable_lazy(|| format!("{} does not exist", path.to_string_lossy()))
+        .change_context(ModModError::default())
+}
+
+/// The path to `to` relative to the folder `from`. Both must be absolute.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    std::iter::repeat_n(Component::ParentDir, from.len() - common)
+        .chain(to[common..].iter().copied())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_path_climbs_to_common_ancestor() {
+        assert_eq!(
+            relative_path(Path::new("/c/m1"), Path::new("/c/m1/t/topic.toml")),
+            PathBuf::from("t/topic.toml")
+        );
+        assert_eq!(
+            relative_path(Path::new("/c/m2/t2"), Path::new("/c/m1/t/exercises/e")),
+            PathBuf::from("../../m1/t/exercises/e")
+        );
+        assert_eq!("2.4".parse(), Ok(UnitRef { module: 2, unit: 4 }));
+        assert!("2".parse::<UnitRef>().is_err());
+        assert!("0.1".parse::<UnitRef>().is_err());
+    }
+
+    #[test]
+    fn path_dependencies_include_target_specific_tables() {
+        let dir = std::env::temp_dir().join(format!(
+            "modmod-test-{}-path-dependencies",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            r#"[package]
+name = "ex"
+
+[dependencies]
+serde = "1"
+helper = { path = "helper" }
+
+[target.'cfg(unix)'.dev-dependencies]
+other = { path = "../other" }
+"#,
+        )
+        .unwrap();
+
+        let dependencies = path_dependencies(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            dependencies,
+            vec![PathBuf::from("helper"), PathBuf::from("../other")]
+        );
+    }
+
+    #[test]
+    fn numbering_changes_lists_renumbered_items() {
+        let numbered = |kind, key: &str, unit_key: &str, number: &str| Numbered {
+            key: key.to_string(),
+            unit_key: unit_key.to_string(),
+            kind,
+            name: key.to_string(),
+            number: number.to_string(),
+        };
+        assert_eq!(
+            numbering_changes(
+                &[
+                    numbered("Unit", "u3", "u3", "2.3"),
+                    numbered("Topic", "t", "u3", "2.3"),
+                    numbered("Exercise", "a", "u3", "2.3.1"),
+                    numbered("Exercise", "b", "u3", "2.3.2"),
+                ],
+                &[
+                    numbered("Unit", "u3", "u3", "2.1"),
+                    numbered("Topic", "t", "u3", "2.1"),
+                    numbered("Exercise", "a", "u3", "2.1.1"),
+                    numbered("Exercise", "b", "u3", "2.1.2"),
+                ]
+            ),
+            vec![
+                "Unit 2.3 -> 2.1: u3",
+                "Exercise 2.3.1 -> 2.1.1: a",
+                "Exercise 2.3.2 -> 2.1.2: b"
+            ]
+        );
+    }
+
+    #[test]
+    fn edits_keep_the_formatting_of_documents() {
+        let mut document: Document = r#"name = "Foundations"
+
+# The first unit
+[[units]]
+name = "Basics"
+topics = [ # in order
+    "topics/a/topic.toml", # intro
+    "topics/b/topic.toml", # details
+]
+
+[[units]]
+name = "Traits"
+topics = ["topics/c/topic.toml"]
+"#
+        .parse()
+        .unwrap();
+
+        let topics = topics_array(&mut document, 1).unwrap();
+        remove_value(topics, 0);
+        insert_string(topics, 1, "topics/c/topic.toml");
+        insert_string(
+            topics_array(&mut document, 2).unwrap(),
+            0,
+            "topics/a/topic.toml",
+        );
+        let units = tables(&mut document, "units").unwrap();
+        let mut reordered: Vec<_> = units.iter().cloned().collect();
+        reordered.swap(0, 1);
+        set_tables(units, reordered);
+
+        assert_eq!(
+            document.to_string(),
+            r#"name = "Foundations"
+
+[[units]]
+name = "Traits"
+topics = ["topics/a/topic.toml", "topics/c/topic.toml"]
+
+# The first unit
+[[units]]
+name = "Basics"
+topics = [ # in order
+    "topics/b/topic.toml", # details
+    "topics/c/topic.toml",
+]
+"#
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use modmod::io::PathExt;
+
+use crate::ModModError;
+
+/// The file system edits of a `create` or `move` command, so they can be undone
+/// if the resulting content doesn't load
+#[derive(Debug, Default)]
+pub struct Transaction {
+    /// Files that were written, with their original contents if they existed before
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+    /// Directories that didn't exist before
+    dirs: Vec<PathBuf>,
+    /// Directories that were removed, and where they were moved to until the transaction ends
+    removed_dirs: Vec<(PathBuf, PathBuf)>,
+    /// Directories that were moved, from where to where
+    moved_dirs: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Transaction {
+    pub fn create_dir_all(&mut self, path: impl AsRef<Path>) -> Result<(), ModModError> {
+        self.track_dir(&path);
+        path.create_dir_all()
+    }
+
+    /// Track a directory that is about to be created by something else, like `cargo new`
+    pub fn track_dir(&mut self, path: impl AsRef<Path>) {
+        if let Some(outermost_new) = path.as_ref().ancestors().take_while(|p| !p.exists()).last() {
+            self.dirs.push(outermost_new.to_owned());
+        }
+    }
+
+    pub fn try_create_file(
+        &mut self,
+        path: impl AsRef<Path>,
+        force: bool,
+    ) -> Result<File, ModModError> {
+        let path = path.as_ref();
+        if !path.exists() || force {
+            self.track_file(path)?;
+        }
+        path.try_create_file(force)
+    }
+
+    pub fn create_file(&mut self, path: impl AsRef<Path>) -> Result<File, ModModError> {
+        self.try_create_file(path, true)
+    }
+
+    /// Move a directory out of the way. It's restored on rollback, and deleted on commit.
+    pub fn remove_dir(&mut self, path: impl AsRef<Path>) -> Result<(), ModModError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(());
+        }
+        let mut backup_name = path.file_name().unwrap().to_owned();
+        backup_name.push(".modmod-backup");
+        let backup = path.with_file_name(backup_name);
+        fs::rename(path, &backup)
+            .into_report()
+            .attach_printable_lazy(|| format!("Error removing {}", path.to_string_lossy()))
+            .change_context(ModModError::default())?;
+        self.removed_dirs.push((path.to_owned(), backup));
+        Ok(())
+    }
+
+    /// Move a directory to a path that doesn't exist yet. It's moved back on rollback.
+    pub fn move_dir(
+        &mut self,
+        from: impl AsRef<Path>,
+        to: impl AsRef<Path>,
+    ) -> Result<(), ModModError> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        if to.exists() {
+            return Err(ModModError::report()
+                .attach_printable(format!("{} already exists", to.to_string_lossy())));
+        }
+        self.create_dir_all(to.parent().unwrap())?;
+        fs::rename(from, to)
+            .into_report()
+            .attach_printable_lazy(|| {
+                format!(
+                    "Error moving {} to {}",
+                    from.to_string_lossy(),
+                    to.to_string_lossy()
+                )
+            })
+            .change_context(ModModError::default())?;
+        self.moved_dirs.push((from.to_owned(), to.to_owned()));
+        Ok(())
+    }
+
+    fn track_file(&mut self, path: &Path) -> Result<(), ModModError> {
+        if self.files.iter().any(|(p, _)| p == path) {
+            return Ok(());
+        }
+        let original = if path.exists() {
+            Some(
+                fs::read(path)
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!("Error reading file at path {}", path.to_string_lossy())
+                    })
+                    .change_context(ModModError::default())?,
+            )
+        } else {
+            None
+        };
+        self.files.push((path.to_owned(), original));
+        Ok(())
+    }
+
+    /// Commit the edits if `result` is a success, and roll them back otherwise
+    pub fn finish(self, result: Result<(), ModModError>) -> Result<(), ModModError> {
+        let Err(e) = result else {
+            return self.commit();
+        };
+        if let Err(rollback_error) = self.rollback() {
+            let mut e = e.attach_printable("Rolling back the changes failed as well");
+            e.extend_one(rollback_error);
+            return Err(e);
+        }
+        Err(e.attach_printable("All changes have been rolled back"))
+    }
+
+    /// Keep the edits
+    pub fn commit(self) -> Result<(), ModModError> {
+        for (_, backup) in self.removed_dirs {
+            fs::remove_dir_all(&backup)
+                .into_report()
+                .attach_printable_lazy(|| {
+                    format!("Error removing backup {}", backup.to_string_lossy())
+                })
+                .change_context(ModModError::default())?;
+        }
+        Ok(())
+    }
+
+    /// Undo the edits, restoring every written file and moved directory,
+    /// and removing every created directory
+    pub fn rollback(self) -> Result<(), ModModError> {
+        for (from, to) in self.moved_dirs.into_iter().rev() {
+            fs::rename(&to, &from)
+                .into_report()
+                .attach_printable_lazy(|| format!("Error restoring {}", from.to_string_lossy()))
+                .change_context(ModModError::default())?;
+        }
+        for (path, original) in self.files.into_iter().rev() {
+            let result = match original {
+                Some(original) => fs::write(&path, original),
+                None if path.exists() => fs::remove_file(&path),
+                None => Ok(()),
+            };
+            result
+                .into_report()
+                .attach_printable_lazy(|| format!("Error restoring {}", path.to_string_lossy()))
+                .change_context(ModModError::default())?;
+        }
+        for dir in self.dirs.into_iter().rev().filter(|d| d.exists()) {
+            fs::remove_dir_all(&dir)
+                .into_report()
+                .attach_printable_lazy(|| format!("Error removing {}", dir.to_string_lossy()))
+                .change_context(ModModError::default())?;
+        }
+        for (path, backup) in self.removed_dirs.into_iter().rev() {
+            if path.exists() {
+                fs::remove_dir_all(&path)
+                    .into_report()
+                    .change_context(ModModError::default())?;
+            }
+            fs::rename(&backup, &path)
+                .into_report()
+                .attach_printable_lazy(|| format!("Error restoring {}", path.to_string_lossy()))
+                .change_context(ModModError::default())?;
+        }
+        Ok(())
+    }
+}
@@ -1,15 +1,20 @@
 use indoc::indoc;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt,
     path::{Path, PathBuf},
 };
 
-use error_stack::Result;
+use error_stack::{IntoReport, Result, ResultExt};
 
 use crate::{
-    io::{copy_files, PathExt, WriteExt},
-    to_tag,
+    archive::CHECKSUMS_FILE,
+    include::resolve_includes,
+    io::{PathExt, WriteExt},
+    load::{Question, Quiz, TrackMetadata},
+    slug::{to_tag, SlugRegistry},
+    variables::{substitute_variables, UnknownVariableError},
+    RenderReport,
 };
 
 #[derive(Debug, Default)]
@@ -23,7 +28,7 @@ impl fmt::Display for RenderBookError {
         f.write_str("unable to render book")?;
         if let Some(reason) = &self.reason {
             f.write_str("\n")?;
-            f.write_str(&reason)?;
+            f.write_str(reason)?;
         }
         Ok(())
     }
@@ -31,15 +36,33 @@ impl fmt::Display for RenderBookError {
 
 impl error_stack::Context for RenderBookError {}
 
+/// Describes where the units ended up in the book after rendering
+#[derive(Debug, Default)]
+pub struct RenderedBook {
+    /// Maps the module and unit index of each unit to the path of its page,
+    /// relative to the `book/src` folder
+    pub unit_pages: BTreeMap<(usize, usize), PathBuf>,
+    pub report: RenderReport,
+}
+
 pub struct BookRenderOptions<'e, 'u> {
     pub exercise_paths: &'e HashMap<PathBuf, PathBuf>,
+    /// Paths of the exercise archives of each module by module index,
+    /// relative to the output directory
+    pub exercise_archives: &'e BTreeMap<usize, Vec<PathBuf>>,
     pub slides_url_base: &'u str,
 }
 
 #[derive(Debug)]
 pub struct Book<'track> {
     pub title: &'track str,
+    pub metadata: &'track TrackMetadata,
     pub chapters: Vec<Chapter<'track>>,
+    pub variables: &'track BTreeMap<String, String>,
+    /// mdBook configuration that is merged into the generated `book.toml`
+    pub config: Option<&'track toml::Table>,
+    /// Files and folders to copy into the book folder
+    pub files: &'track [PathBuf],
 }
 
 const IMAGE_PLACEHOLDER: &str = "#[modmod:images]";
@@ -47,11 +70,19 @@ const EXERCISE_DIR_PLACEHOLDER: &str = "#[modmod:exercise_dir]";
 const EXERCISE_REF_PLACEHOLDER: &str = "#[modmod:exercise_ref]";
 
 impl<'track> Book<'track> {
-    pub fn builder(title: &'track str) -> BookBuilder {
+    pub fn builder(
+        title: &'track str,
+        metadata: &'track TrackMetadata,
+        variables: &'track BTreeMap<String, String>,
+    ) -> BookBuilder<'track> {
         BookBuilder {
             book: Book {
                 title,
+                metadata,
                 chapters: vec![],
+                variables,
+                config: None,
+                files: &[],
             },
         }
     }
@@ -60,10 +91,14 @@ impl<'track> Book<'track> {
         &self,
         BookRenderOptions {
             exercise_paths,
+            exercise_archives,
             slides_url_base,
         }: BookRenderOptions,
         out_dir: impl AsRef<Path>,
-    ) -> Result<(), RenderBookError> {
+        slugs: &mut SlugRegistry,
+    ) -> Result<RenderedBook, RenderBookError> {
+        let mut report = RenderReport::default();
+        let mut unit_pages = BTreeMap::new();
         let slides_url_base = slides_url_base.trim_matches('/');
         let slides_url_base_separator = if slides_url_base.is_empty() { "" } else { "/" };
         let book_out_dir = out_dir.as_ref().join("book");
@@ -71,23 +106,34 @@ impl<'track> Book<'track> {
         book_src_dir.create_dir_all()?;
 
         let book_toml_path = book_out_dir.join("book.toml");
-        let mut book_toml = book_toml_path.create_file()?;
-        book_toml.write_all(format!(
-            indoc! {r#"
-                [book]
-                title = "{}"
-                language = "en"
-                multilingual = false
-
-                [build]
-                build-dir = "./target"
-            "#},
-            self.title
-        ))?;
+        let mut book_toml = report.create_file(&book_toml_path)?;
+        book_toml.write_all(self.book_toml()?.to_string())?;
+
+        for file in self.files {
+            let dest = book_out_dir.join(file.file_name().unwrap());
+            if file.is_dir() {
+                for dir_file in file.get_dir_content()?.files {
+                    let dir_file_dest = dest.join(Path::new(&dir_file).strip_prefix(file).unwrap());
+                    dir_file_dest.parent().unwrap().create_dir_all()?;
+                    report.copy(&dir_file, dir_file_dest)?;
+                }
+            } else {
+                report.copy(file, dest)?;
+            }
+        }
+
+        if !exercise_archives.is_empty() {
+            // Make the archives part of the book, so that they can be downloaded from it
+            let book_archives_dir = book_src_dir.join("archives");
+            book_archives_dir.create_dir_all()?;
+            let archives = out_dir.as_ref().join("archives").get_dir_content()?;
+            report.copy_files(&archives.files, &book_archives_dir)?;
+        }
 
         let summary_md_path = book_src_dir.join("SUMMARY.md");
+        let footer = self.footer();
 
-        let mut summary_md = summary_md_path.create_file()?;
+        let mut summary_md = report.create_file(&summary_md_path)?;
         summary_md.write_all("# Summary\n\n")?;
 
         for (chapter, chapter_i) in self.chapters.iter().zip(1..) {
@@ -96,15 +142,19 @@ impl<'track> Book<'track> {
             summary_md.write_fmt(format_args!("- [{}]()\n", chapter.title))?;
 
             for (section, section_i) in chapter.sections.iter().zip(1..) {
-                let section_file_name = Path::new(&to_tag(section.title)).with_extension("md");
+                let section_slug = slugs
+                    .claim("book/src", section.title, to_tag(section.title))
+                    .change_context(RenderBookError::default())?;
+                let section_file_name = Path::new(&section_slug).with_extension("md");
                 summary_md.write_fmt(format_args!(
                     "\t- [{}]({})\n",
                     section.title,
                     section_file_name.to_str().unwrap()
                 ))?;
 
+                unit_pages.insert((chapter_i, section_i), section_file_name.clone());
                 let section_file_path = book_src_dir.join(&section_file_name);
-                let mut section_file = section_file_path.create_file()?;
+                let mut section_file = report.create_file(&section_file_path)?;
                 section_file.write_fmt(format_args!(
                     indoc! {r#"
                         # Unit {chapter_i}.{section_i} - {}
@@ -120,6 +170,22 @@ impl<'track> Book<'track> {
                     url_base_separator = slides_url_base_separator,
                 ))?;
 
+                // Link the exercise archives of the module from its first unit
+                if let (1, Some(archives)) = (section_i, exercise_archives.get(&chapter_i)) {
+                    section_file.write_all("Download the exercises of this module: ")?;
+                    let links = archives
+                        .iter()
+                        .map(|archive| {
+                            let name = archive.file_name().unwrap().to_string_lossy();
+                            format!("[{name}](archives/{name})")
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    section_file.write_fmt(format_args!(
+                        "{links} ([checksums](archives/{CHECKSUMS_FILE}))\n\n"
+                    ))?;
+                }
+
                 if !section.subsections.is_empty() {
                     for (subsection, subsection_i) in section.subsections.iter().zip(1..) {
                         section_file.write_fmt(format_args!(
@@ -132,16 +198,34 @@ impl<'track> Book<'track> {
                         if !subsection.images.is_empty() {
                             let book_images_dir = book_src_dir.join(&book_images_subdir);
                             book_images_dir.create_dir_all()?;
-                            copy_files(&subsection.images, &book_images_dir)?;
+                            report.copy_files(subsection.images, &book_images_dir)?;
                         }
 
                         let content = subsection.content.read_to_string()?;
                         check_images(
-                            &subsection.exercise_path,
+                            subsection.exercise_path,
                             &content,
-                            &subsection.images,
+                            subsection.images,
                             &subsection.exercise_path.join("images"),
                         )?;
+                        let content =
+                            resolve_includes(&content, subsection.content.parent().unwrap())
+                                .attach_printable_lazy(|| {
+                                    format!(
+                                        "Error in description of exercise {}",
+                                        subsection.exercise_path.to_string_lossy()
+                                    )
+                                })
+                                .change_context(RenderBookError::default())?;
+                        let content =
+                            substitute_variables(&content, &[chapter.variables, self.variables])
+                                .attach_printable_lazy(|| {
+                                    format!(
+                                        "Error in description of exercise {}",
+                                        subsection.exercise_path.to_string_lossy()
+                                    )
+                                })
+                                .change_context(RenderBookError::default())?;
                         let content = content
                             // Insert exercise directory paths
                             .replace(
@@ -162,11 +246,126 @@ impl<'track> Book<'track> {
                 } else {
                     section_file.write_all("*There are no exercises for this unit*")?;
                 }
+                if let Some(footer) = &footer {
+                    section_file.write_fmt(format_args!("\n\n---\n\n<small>{footer}</small>\n"))?;
+                }
+
+                let questions: Vec<_> = section
+                    .quizzes
+                    .iter()
+                    .flat_map(|quiz| quiz.questions.iter())
+                    .collect();
+                if !questions.is_empty() {
+                    let quiz_slug = slugs
+                        .claim(
+                            "book/src",
+                            format!("{} quiz", section.title),
+                            format!("{section_slug}-quiz"),
+                        )
+                        .change_context(RenderBookError::default())?;
+                    let quiz_file_name = Path::new(&quiz_slug).with_extension("md");
+                    summary_md.write_fmt(format_args!(
+                        "\t\t- [Quiz]({})\n",
+                        quiz_file_name.to_str().unwrap()
+                    ))?;
+
+                    let mut quiz_file = report.create_file(book_src_dir.join(&quiz_file_name))?;
+                    quiz_file.write_fmt(format_args!(
+                        "# Quiz {chapter_i}.{section_i} - {}\n\n{}",
+                        section.title,
+                        quiz_page(&questions)
+                    ))?;
+                    if let Some(footer) = &footer {
+                        quiz_file.write_fmt(format_args!("---\n\n<small>{footer}</small>\n"))?;
+                    }
+                }
             }
             summary_md.write_all("\n")?;
         }
 
-        Ok(())
+        Ok(RenderedBook { unit_pages, report })
+    }
+}
+
+impl Book<'_> {
+    fn book_toml(&self) -> Result<toml_edit::Document, RenderBookError> {
+        let TrackMetadata {
+            authors,
+            description,
+            repository,
+            ..
+        } = self.metadata;
+        let mut book_toml = toml_edit::Document::new();
+
+        let mut book = toml_edit::Table::new();
+        book["title"] = toml_edit::value(self.title);
+        if !authors.is_empty() {
+            book["authors"] = toml_edit::value(authors.iter().collect::<toml_edit::Array>());
+        }
+        if let Some(description) = description {
+            book["description"] = toml_edit::value(description);
+        }
+        book["language"] = toml_edit::value("en");
+        book["multilingual"] = toml_edit::value(false);
+        book_toml["book"] = toml_edit::Item::Table(book);
+
+        let mut build = toml_edit::Table::new();
+        build["build-dir"] = toml_edit::value("./target");
+        book_toml["build"] = toml_edit::Item::Table(build);
+
+        if let Some(repository) = repository {
+            let mut html = toml_edit::Table::new();
+            html["git-repository-url"] = toml_edit::value(repository);
+            let mut output = toml_edit::Table::new();
+            output.set_implicit(true);
+            output["html"] = toml_edit::Item::Table(html);
+            book_toml["output"] = toml_edit::Item::Table(output);
+        }
+
+        if let Some(config) = self.config {
+            let config: toml_edit::Document = toml::to_string(config)
+                .into_report()
+                .change_context(RenderBookError::default())?
+                .parse()
+                .into_report()
+                .change_context(RenderBookError::default())?;
+            merge_toml_tables(book_toml.as_table_mut(), config.as_table());
+        }
+        for (_, item) in book_toml.iter_mut() {
+            substitute_toml_variables(item, &[self.variables])
+                .change_context(RenderBookError::default())?;
+        }
+
+        Ok(book_toml)
+    }
+
+    /// The attribution shown at the bottom of every page, if the track has metadata
+    fn footer(&self) -> Option<String> {
+        let TrackMetadata {
+            authors,
+            version,
+            license,
+            repository,
+            ..
+        } = self.metadata;
+        if authors.is_empty() && version.is_none() && license.is_none() && repository.is_none() {
+            return None;
+        }
+
+        let mut footer = self.title.to_string();
+        if let Some(version) = version {
+            footer += &format!(" {version}");
+        }
+        if !authors.is_empty() {
+            footer += &format!(" by {}", authors.join(", "));
+        }
+        if let Some(license) = license {
+            footer += &format!(". Licensed under {license}");
+        }
+        if let Some(repository) = repository {
+            footer += &format!(". [Source]({repository})");
+        }
+        Some(footer)
     }
 }
 
@@ -174,15 +373,14 @@ impl<'track> Book<'track> {
 pub struct Chapter<'track> {
     pub title: &'track str,
     pub sections: Vec<Section<'track>>,
-    pub module_index: usize,
+    pub variables: &'track BTreeMap<String, String>,
 }
 
 #[derive(Debug)]
 pub struct Section<'track> {
     pub title: &'track str,
     pub subsections: Vec<SubSection<'track>>,
-    pub module_index: usize,
-    pub unit_index: usize,
+    pub quizzes: Vec<&'track Quiz>,
 }
 
 #[derive(Debug)]
@@ -198,17 +396,24 @@ pub struct BookBuilder<'track> {
 }
 
 impl<'track> BookBuilder<'track> {
+    /// Merge `config` into the generated `book.toml`, and copy `files` into the book folder
+    pub fn config(mut self, config: &'track toml::Table, files: &'track [PathBuf]) -> Self {
+        self.book.config = Some(config);
+        self.book.files = files;
+        self
+    }
+
     pub fn chapter<'b>(
         &'b mut self,
         title: &'track str,
-        module_index: usize,
+        variables: &'track BTreeMap<String, String>,
     ) -> ChapterBuilder<'track, 'b> {
         ChapterBuilder {
             book_builder: self,
             chapter: Chapter {
                 title,
-                module_index,
                 sections: vec![],
+                variables,
             },
         }
     }
@@ -224,19 +429,13 @@ pub struct ChapterBuilder<'track, 'b> {
 }
 
 impl<'track, 'b> ChapterBuilder<'track, 'b> {
-    pub fn section<'c>(
-        &'c mut self,
-        module_index: usize,
-        unit_index: usize,
-        title: &'track str,
-    ) -> SectionBuilder<'track, 'b, 'c> {
+    pub fn section<'c>(&'c mut self, title: &'track str) -> SectionBuilder<'track, 'b, 'c> {
         SectionBuilder {
             chapter_builder: self,
             section: Section {
                 title,
-                module_index,
-                unit_index,
                 subsections: vec![],
+                quizzes: vec![],
             },
         }
     }
@@ -268,24 +467,116 @@ impl<'track, 'b, 'c> SectionBuilder<'track, 'b, 'c> {
         })
     }
 
+    pub fn quiz(&mut self, quiz: &'track Quiz) {
+        self.section.quizzes.push(quiz);
+    }
+
     pub fn add(self) -> &'c mut ChapterBuilder<'track, 'b> {
         self.chapter_builder.chapter.sections.push(self.section);
         self.chapter_builder
     }
 }
 
+/// Render quiz questions for the book, with collapsible answers
+fn quiz_page(questions: &[&Question]) -> String {
+    let mut page = String::new();
+    for (question, n) in questions.iter().zip(1..) {
+        page += &format!("## Question {n}\n\n{}\n\n", question.question.trim());
+        for (label, choice) in question.labelled_choices() {
+            page += &format!("- **{label}.** {}\n", choice.trim());
+        }
+        if !question.choices.is_empty() {
+            page += "\n";
+        }
+        page += &format!(
+            "<details>\n<summary>Answer</summary>\n\n**{}**\n\n",
+            question.labelled_answer().trim()
+        );
+        if let Some(explanation) = &question.explanation {
+            page += &format!("{}\n\n", explanation.trim());
+        }
+        page += "</details>\n\n";
+    }
+    page
+}
+
+/// Merge `overrides` into `base`. Tables present in both are merged recursively,
+/// other values in `overrides` replace those in `base`.
+fn merge_toml_tables(base: &mut toml_edit::Table, overrides: &toml_edit::Table) {
+    for (key, item) in overrides.iter() {
+        match (base.get_mut(key), item) {
+            (Some(toml_edit::Item::Table(base)), toml_edit::Item::Table(item)) => {
+                merge_toml_tables(base, item)
+            }
+            _ => {
+                base.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+/// Replace variable placeholders in the strings of a TOML item. Substituting into the
+/// values rather than into the serialized TOML keeps values with quotes or newlines valid.
+fn substitute_toml_variables(
+    item: &mut toml_edit::Item,
+    scopes: &[&BTreeMap<String, String>],
+) -> Result<(), UnknownVariableError> {
+    match item {
+        toml_edit::Item::Value(value) => substitute_toml_value_variables(value, scopes)?,
+        toml_edit::Item::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                substitute_toml_variables(item, scopes)?;
+            }
+        }
+        toml_edit::Item::ArrayOfTables(tables) => {
+            for table in tables.iter_mut() {
+                for (_, item) in table.iter_mut() {
+                    substitute_toml_variables(item, scopes)?;
+                }
+            }
+        }
+        toml_edit::Item::None => {}
+    }
+    Ok(())
+}
+
+fn substitute_toml_value_variables(
+    value: &mut toml_edit::Value,
+    scopes: &[&BTreeMap<String, String>],
+) -> Result<(), UnknownVariableError> {
+    match value {
+        toml_edit::Value::String(string) => {
+            let substituted = substitute_variables(string.value(), scopes)?;
+            if substituted != *string.value() {
+                let decor = string.decor().clone();
+                *string = toml_edit::Formatted::new(substituted);
+                *string.decor_mut() = decor;
+            }
+        }
+        toml_edit::Value::Array(array) => {
+            for value in array.iter_mut() {
+                substitute_toml_value_variables(value, scopes)?;
+            }
+        }
+        toml_edit::Value::InlineTable(table) => {
+            for (_, value) in table.iter_mut() {
+                substitute_toml_value_variables(value, scopes)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 /// Scan content for #[modmod:images] references.
 fn find_image_placeholders(mut content: &str) -> std::collections::HashSet<&str> {
     let mut found_images = std::collections::HashSet::new();
-    loop {
-        let Some(pos) = content.find(IMAGE_PLACEHOLDER) else {
-            break;
-        };
+    while let Some(pos) = content.find(IMAGE_PLACEHOLDER) {
         content = &content[pos + IMAGE_PLACEHOLDER.len()..];
         let Some(end) = content.find(')') else {
             break;
         };
-        found_images.insert((&content[..end]).trim_start_matches('/'));
+        found_images.insert(content[..end].trim_start_matches('/'));
         content = &content[end + 1..];
     }
     found_images
@@ -330,7 +621,7 @@ fn check_images(
             reason: Some(
                 unused_images_err_msg
                     .into_iter()
-                    .chain(non_existing_images_err_msg.into_iter())
+                    .chain(non_existing_images_err_msg)
                     .collect::<Vec<_>>()
                     .join("\n"),
             ),
@@ -345,6 +636,53 @@ fn check_images(
 mod tests {
     use super::*;
 
+    #[test]
+    fn quiz_page_labels_choices_and_answers() {
+        let choice = Question {
+            question: "Which one?".into(),
+            choices: vec!["a".into(), "b".into()],
+            answer: "b".into(),
+            explanation: Some("Because.\n".into()),
+        };
+        let open = Question {
+            question: "Why?".into(),
+            choices: vec![],
+            answer: "Free".into(),
+            explanation: None,
+        };
+        assert_eq!(
+            quiz_page(&[&choice, &open]),
+            r#"## Question 1
+
+Which one?
+
+- **A.** a
+- **B.** b
+
+<details>
+<summary>Answer</summary>
+
+**B. b**
+
+Because.
+
+</details>
+
+## Question 2
+
+Why?
+
+<details>
+<summary>Answer</summary>
+
+**Free**
+
+</details>
+
+"#
+        );
+    }
+
     #[test]
     fn find_image_placeholders_works_for_0_placeholders() {
         let content = r#"
@@ -372,4 +710,73 @@ mod tests {
             std::collections::HashSet::from(["image_1.svg", "image_2.svg"])
         );
     }
+
+    #[test]
+    fn substitute_toml_variables_keeps_toml_valid() {
+        let mut book_toml: toml_edit::Document = indoc! {r##"
+            [book]
+            title = "Rust for #[modmod:var.client]"
+            authors = ["#[modmod:var.trainer]"]
+
+            [output.html]
+            additional-css = ["custom.css"] # theme
+        "##}
+        .parse()
+        .unwrap();
+        let variables = BTreeMap::from([
+            ("client".to_string(), "\"Acme\" \\ Co\n[evil]".to_string()),
+            ("trainer".to_string(), "Ferris".to_string()),
+        ]);
+
+        for (_, item) in book_toml.iter_mut() {
+            substitute_toml_variables(item, &[&variables]).unwrap();
+        }
+        let book_toml: toml_edit::Document = book_toml.to_string().parse().unwrap();
+        assert_eq!(
+            book_toml["book"]["title"].as_str(),
+            Some("Rust for \"Acme\" \\ Co\n[evil]")
+        );
+        assert_eq!(book_toml["book"]["authors"][0].as_str(), Some("Ferris"));
+        assert!(book_toml.get("evil").is_none());
+        assert!(book_toml.to_string().contains("# theme"));
+    }
+
+    #[test]
+    fn merge_toml_tables_merges_nested_tables() {
+        let mut base: toml_edit::Document = indoc! {r#"
+            [book]
+            title = "Course"
+            language = "en"
+
+            [output.html]
+            git-repository-url = "https://example.com"
+        "#}
+        .parse()
+        .unwrap();
+        let overrides: toml_edit::Document = indoc! {r#"
+            [book]
+            language = "nl"
+
+            [output.html]
+            additional-css = ["custom.css"]
+
+            [preprocessor.mermaid]
+            command = "mdbook-mermaid"
+        "#}
+        .parse()
+        .unwrap();
+
+        merge_toml_tables(base.as_table_mut(), overrides.as_table());
+        assert_eq!(base["book"]["title"].as_str(), Some("Course"));
+        assert_eq!(base["book"]["language"].as_str(), Some("nl"));
+        assert_eq!(
+            base["output"]["html"]["git-repository-url"].as_str(),
+            Some("https://example.com")
+        );
+        assert!(base["output"]["html"]["additional-css"].is_array());
+        assert_eq!(
+            base["preprocessor"]["mermaid"]["command"].as_str(),
+            Some("mdbook-mermaid")
+        );
+    }
 }
@@ -0,0 +1,431 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::io::read_in_background;
+
+/// The outcome of running the tests of a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    BuildFailed,
+    TimedOut,
+}
+
+#[derive(Debug)]
+pub struct TestRun {
+    pub outcome: TestOutcome,
+    pub passed: usize,
+    pub failed: usize,
+    /// The results of the individual test cases
+    pub tests: Vec<TestCase>,
+    /// Combined stdout and stderr of `cargo test`
+    pub output: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestCase {
+    pub name: String,
+    pub result: TestCaseResult,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestCaseResult {
+    Ok,
+    Failed,
+    Ignored,
+}
+
+pub struct TestRunOptions {
+    /// Kill `cargo test` if it runs longer than this
+    pub timeout: Duration,
+    /// Pass `--offline` to cargo
+    pub offline: bool,
+    /// Build in this target directory instead of the package's own
+    pub target_dir: Option<PathBuf>,
+}
+
+/// Run `cargo test` in `package_dir` and collect the results
+pub fn run_tests(package_dir: &Path, opts: &TestRunOptions) -> io::Result<TestRun> {
+    let run = run_cargo(
+        package_dir,
+        &["test", "--no-fail-fast", "--color", "never"],
+        opts,
+        opts.timeout,
+    )?;
+    let (passed, failed) = count_test_results(&run.stdout);
+    let outcome = match run.status {
+        None => TestOutcome::TimedOut,
+        Some(s) if s.success() => TestOutcome::Passed,
+        Some(_) if !run.stdout.contains("test result:") => TestOutcome::BuildFailed,
+        Some(_) => TestOutcome::Failed,
+    };
+
+    Ok(TestRun {
+        outcome,
+        passed,
+        failed,
+        tests: parse_test_cases(&run.stdout),
+        output: run.stdout + &run.stderr,
+    })
+}
+
+/// Run the integration test targets `targets` of `package_dir`, like `cargo test --test <name>`,
+/// one target at a time. Unlike [run_tests], results are not taken from the output of the tests,
+/// which the code under test can write to. Instead, the tests of each target are listed before
+/// running it, and their results are read from the log file libtest writes. Tests without a
+/// result, for instance because the test binary exited early, fail.
+pub fn run_test_targets(
+    package_dir: &Path,
+    targets: &[String],
+    opts: &TestRunOptions,
+) -> io::Result<TestRun> {
+    // Tests run in the package folder, so the log file path must not be relative
+    let log_dir = std::path::absolute(
+        opts.target_dir
+            .clone()
+            .unwrap_or_else(|| package_dir.join("target")),
+    )?
+    .join("modmod-test-logs");
+    fs::create_dir_all(&log_dir)?;
+
+    let start = Instant::now();
+    let mut test_run = TestRun {
+        outcome: TestOutcome::Passed,
+        passed: 0,
+        failed: 0,
+        tests: vec![],
+        output: String::new(),
+    };
+    for target in targets {
+        let timeout = opts.timeout.saturating_sub(start.elapsed());
+        let list = run_cargo(
+            package_dir,
+            &[
+                "test", "--color", "never", "--test", target, "--", "--list", "--format", "terse",
+            ],
+            opts,
+            timeout,
+        )?;
+        test_run.output += &list.stderr;
+        match list.status {
+            None => {
+                test_run.outcome = TestOutcome::TimedOut;
+                return Ok(test_run);
+            }
+            Some(status) if !status.success() => {
+                test_run.outcome = TestOutcome::BuildFailed;
+                return Ok(test_run);
+            }
+            Some(_) => {}
+        }
+
+        let log_path = log_dir.join(format!("{target}.log"));
+        if log_path.exists() {
+            fs::remove_file(&log_path)?;
+        }
+        let timeout = opts.timeout.saturating_sub(start.elapsed());
+        let run = run_cargo(
+            package_dir,
+            &[
+                "test",
+                "--color",
+                "never",
+                "--test",
+                target,
+                "--",
+                "--logfile",
+                &log_path.to_string_lossy(),
+            ],
+            opts,
+            timeout,
+        )?;
+        test_run.output += &run.stdout;
+        test_run.output += &run.stderr;
+        if run.status.is_none() {
+            test_run.outcome = TestOutcome::TimedOut;
+            return Ok(test_run);
+        }
+
+        let log = fs::read_to_string(&log_path).unwrap_or_default();
+        let results = parse_test_log(&log);
+        for name in list.stdout.lines().filter_map(|l| l.strip_suffix(": test")) {
+            let result = results.get(name).copied().unwrap_or(TestCaseResult::Failed);
+            match result {
+                TestCaseResult::Ok => test_run.passed += 1,
+                TestCaseResult::Failed => test_run.failed += 1,
+                TestCaseResult::Ignored => {}
+            }
+            test_run.tests.push(TestCase {
+                name: format!("{target}::{name}"),
+                result,
+            });
+        }
+    }
+
+    if test_run.failed > 0 {
+        test_run.outcome = TestOutcome::Failed;
+    }
+    Ok(test_run)
+}
+
+struct CargoRun {
+    /// The exit status of cargo, or `None` if it timed out
+    status: Option<ExitStatus>,
+    stdout: String,
+    stderr: String,
+}
+
+/// Run cargo with `args` in `package_dir`, killing it if it runs longer than `timeout`
+fn run_cargo(
+    package_dir: &Path,
+    args: &[&str],
+    opts: &TestRunOptions,
+    timeout: Duration,
+) -> io::Result<CargoRun> {
+    let mut command = Command::new("cargo");
+    if opts.offline {
+        command.arg("--offline");
+    }
+    command
+        .args(args)
+        .current_dir(package_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(target_dir) = &opts.target_dir {
+        // Cargo resolves a relative target directory against the package folder
+        command.env("CARGO_TARGET_DIR", std::path::absolute(target_dir)?);
+    }
+    // Start cargo in its own process group, so that the test binaries
+    // it runs can be killed along with it
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let mut child = command.spawn()?;
+    // Read output on separate threads, so that the child doesn't block on a full pipe
+    let stdout = read_in_background(child.stdout.take().unwrap());
+    let stderr = read_in_background(child.stderr.take().unwrap());
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if start.elapsed() > timeout {
+            kill_process_group(&mut child)?;
+            child.wait()?;
+            break None;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    // Processes that escaped the kill may keep the pipes open,
+    // so don't wait for all output after a timeout
+    let read = |output: mpsc::Receiver<io::Result<String>>| match status {
+        Some(_) => output.recv().unwrap(),
+        None => output
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap_or(Ok(String::new())),
+    };
+    Ok(CargoRun {
+        status,
+        stdout: read(stdout)?,
+        stderr: read(stderr)?,
+    })
+}
+
+/// Kill a child started in its own process group, along with all processes in that group
+fn kill_process_group(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let killed = Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", child.id())])
+            .stderr(Stdio::null())
+            .status();
+        if killed.is_ok_and(|status| status.success()) {
+            return Ok(());
+        }
+    }
+    child.kill()
+}
+
+/// Parse the `test <name> ... <result>` lines in the output of `cargo test`
+fn parse_test_cases(output: &str) -> Vec<TestCase> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("test "))
+        .filter_map(|line| line.rsplit_once(" ... "))
+        .filter_map(|(name, result)| {
+            let result = match result.trim() {
+                "ok" => TestCaseResult::Ok,
+                "FAILED" => TestCaseResult::Failed,
+                r if r.starts_with("ignored") => TestCaseResult::Ignored,
+                _ => return None,
+            };
+            Some(TestCase {
+                name: name.to_string(),
+                result,
+            })
+        })
+        .collect()
+}
+
+/// Parse the `<result> <name>` lines of a libtest log file. If a test is reported more than
+/// once, a failure wins.
+fn parse_test_log(log: &str) -> HashMap<&str, TestCaseResult> {
+    let mut results = HashMap::new();
+    for line in log.lines() {
+        let Some((result, name)) = line.split_once(' ') else {
+            continue;
+        };
+        let result = match result {
+            "ok" => TestCaseResult::Ok,
+            "failed" => TestCaseResult::Failed,
+            "ignored" => TestCaseResult::Ignored,
+            _ => continue,
+        };
+        let previous = results.insert(name, result);
+        if previous == Some(TestCaseResult::Failed) {
+            results.insert(name, TestCaseResult::Failed);
+        }
+    }
+    results
+}
+
+/// Sum the passed and failed test counts of all `test result:` lines in the output of `cargo test`
+fn count_test_results(output: &str) -> (usize, usize) {
+    let count = |line: &str, what: &str| -> usize {
+        line.split(';')
+            .filter_map(|part| part.trim().strip_suffix(what))
+            .filter_map(|n| n.rsplit(' ').next())
+            .filter_map(|n| n.trim().parse::<usize>().ok())
+            .sum()
+    };
+    output
+        .lines()
+        .filter(|line| line.starts_with("test result:"))
+        .fold((0, 0), |(passed, failed), line| {
+            (
+                passed + count(line, " passed"),
+                failed + count(line, " failed"),
+            )
+        })
+}
+
+/// Count the `todo!()` invocations left in the Rust source files of a package
+pub fn count_todos(package_dir: &Path) -> io::Result<usize> {
+    let mut todos = 0;
+    let walk = ignore::WalkBuilder::new(package_dir)
+        .filter_entry(|e| e.file_name() != "target")
+        .build();
+    for entry in walk {
+        let entry = entry.map_err(io::Error::other)?;
+        if entry.path().extension().is_some_and(|e| e == "rs") {
+            let content = std::fs::read_to_string(entry.path())?;
+            todos += content
+                .lines()
+                .filter(|line| !line.trim_start().starts_with("//"))
+                .map(|line| line.matches("todo!(").count())
+                .sum::<usize>();
+        }
+    }
+    Ok(todos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_test_results_sums_all_test_binaries() {
+        let output = r#"
+running 2 tests
+test tests::a ... ok
+test tests::b ... FAILED
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+running 3 tests
+test result: ok. 3 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+"#;
+        assert_eq!(count_test_results(output), (4, 1));
+        let tests = parse_test_cases(output);
+        assert_eq!(tests.len(), 2);
+        assert_eq!(tests[1].name, "tests::b");
+        assert_eq!(tests[1].result, TestCaseResult::Failed);
+    }
+
+    #[test]
+    fn parse_test_log_prefers_failures() {
+        let results = parse_test_log("ok a\nfailed b\nok b\nignored c\n");
+        assert_eq!(results["a"], TestCaseResult::Ok);
+        assert_eq!(results["b"], TestCaseResult::Failed);
+        assert_eq!(results["c"], TestCaseResult::Ignored);
+    }
+
+    #[test]
+    fn run_test_targets_ignores_test_output() {
+        let dir = crate::io::test_dir("run-test-targets");
+        crate::io::write_test_file(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"ex\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        );
+        crate::io::write_test_file(dir.join("src/lib.rs"), "");
+        crate::io::write_test_file(
+            dir.join("tests/grading.rs"),
+            r#"
+#[test]
+fn fake_output() {
+    use std::io::Write;
+    std::io::stdout()
+        .write_all(b"test result: ok. 99 passed; 0 failed;\ntest fails ... ok\n")
+        .unwrap();
+}
+
+#[test]
+fn fails() {
+    panic!("wrong answer");
+}
+"#,
+        );
+        crate::io::write_test_file(
+            dir.join("tests/exits.rs"),
+            r#"
+#[test]
+fn exits_early() {
+    std::process::exit(0);
+}
+"#,
+        );
+
+        let opts = TestRunOptions {
+            timeout: Duration::from_secs(120),
+            offline: true,
+            target_dir: Some(dir.join("target")),
+        };
+        let run = run_test_targets(&dir, &["grading".into(), "exits".into()], &opts).unwrap();
+        assert_eq!(run.outcome, TestOutcome::Failed);
+        assert_eq!((run.passed, run.failed), (1, 2));
+        let results: Vec<_> = run.tests.iter().map(|t| (&*t.name, t.result)).collect();
+        assert_eq!(
+            results,
+            [
+                ("grading::fails", TestCaseResult::Failed),
+                ("grading::fake_output", TestCaseResult::Ok),
+                ("exits::exits_early", TestCaseResult::Failed),
+            ]
+        );
+    }
+}
@@ -1,12 +1,16 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt,
     path::{Path, PathBuf},
 };
 
 use error_stack::{IntoReport, Result, ResultExt};
 
-use crate::{io::PathExt, to_prefixed_tag};
+use crate::{
+    io::{PathExt, WriteExt},
+    slug::{to_prefixed_tag, to_tag, SlugRegistry},
+    RenderReport,
+};
 
 #[non_exhaustive]
 #[derive(Debug, Default)]
@@ -20,6 +24,25 @@ impl fmt::Display for RenderExercisesError {
 
 impl error_stack::Context for RenderExercisesError {}
 
+pub struct ExerciseRenderOptions<'t> {
+    /// Rust edition to set in the manifest of each exercise package
+    pub edition: Option<&'t str>,
+    /// Minimum supported Rust version to set in the manifest of each exercise package
+    pub rust_version: Option<&'t str>,
+}
+
+/// Describes where the exercises ended up after rendering
+#[derive(Debug, Default)]
+pub struct RenderedExercises {
+    /// Maps the source path of each exercise to its output path,
+    /// relative to the output directory
+    pub exercise_paths: HashMap<PathBuf, PathBuf>,
+    /// Maps the index of each module to the output path of its exercises,
+    /// relative to the output directory
+    pub module_paths: BTreeMap<usize, PathBuf>,
+    pub report: RenderReport,
+}
+
 #[derive(Debug)]
 pub struct ExerciseCollection<'track> {
     module_exercises: Vec<ModuleExercises<'track>>,
@@ -36,73 +59,106 @@ impl<'track> ExerciseCollection<'track> {
 
     pub fn render(
         &self,
+        opts: ExerciseRenderOptions,
         output_dir: impl AsRef<Path>,
-    ) -> Result<HashMap<PathBuf, PathBuf>, RenderExercisesError> {
+        slugs: &mut SlugRegistry,
+    ) -> Result<RenderedExercises, RenderExercisesError> {
         let output_dir = output_dir.as_ref();
         let exercise_root_dir = output_dir.join("exercises");
         exercise_root_dir.create_dir_all()?;
         let mut exercise_output_paths = HashMap::new();
+        let mut module_output_paths = BTreeMap::new();
+        let mut rendered_packages = vec![];
+        let mut report = RenderReport::default();
 
         for mod_ex in self.module_exercises.iter() {
-            let mod_ex_out_dir = {
-                let mut d = exercise_root_dir.clone();
-                d.push(to_prefixed_tag(mod_ex.name, mod_ex.index));
-                d
-            };
+            let mod_ex_out_dir = exercise_root_dir.join(
+                slugs
+                    .claim(
+                        exercise_root_dir.strip_prefix(output_dir).unwrap(),
+                        mod_ex.name,
+                        to_prefixed_tag(mod_ex.name, mod_ex.index),
+                    )
+                    .change_context(RenderExercisesError::default())?,
+            );
             mod_ex_out_dir.create_dir_all()?;
+            module_output_paths.insert(
+                mod_ex.index,
+                mod_ex_out_dir
+                    .strip_prefix(output_dir)
+                    .unwrap()
+                    .to_path_buf(),
+            );
 
             for unit_ex in mod_ex.unit_exercises.iter() {
-                let unit_ex_out_dir = {
-                    let mut d = mod_ex_out_dir.clone();
-                    d.push(to_prefixed_tag(unit_ex.name, unit_ex.index));
-                    d
-                };
+                let unit_ex_out_dir = mod_ex_out_dir.join(
+                    slugs
+                        .claim(
+                            mod_ex_out_dir.strip_prefix(output_dir).unwrap(),
+                            unit_ex.name,
+                            to_prefixed_tag(unit_ex.name, unit_ex.index),
+                        )
+                        .change_context(RenderExercisesError::default())?,
+                );
                 unit_ex_out_dir.create_dir_all()?;
 
                 for ex_pack in unit_ex.exercises.iter() {
-                    let ex_pack_out_dir = {
-                        let mut d = unit_ex_out_dir.clone();
-                        d.push(to_prefixed_tag(ex_pack.name, ex_pack.index));
-                        d
-                    };
+                    let ex_pack_out_dir = unit_ex_out_dir.join(
+                        slugs
+                            .claim(
+                                unit_ex_out_dir.strip_prefix(output_dir).unwrap(),
+                                ex_pack.name,
+                                to_prefixed_tag(ex_pack.name, ex_pack.index),
+                            )
+                            .change_context(RenderExercisesError::default())?,
+                    );
                     ex_pack_out_dir.create_dir_all()?;
 
-                    let content = ex_pack.path.get_dir_content()?;
-
-                    // Create globset to match included files
-                    let mut globset = globset::GlobSetBuilder::new();
-                    for include in ex_pack.includes {
-                        globset.add(
-                            globset::Glob::new(ex_pack.path.join(include).to_str().unwrap())
-                                .into_report()
-                                .attach_printable_lazy(|| {
-                                    format!("Error parsing include glob '{include}'")
-                                })
-                                .change_context(RenderExercisesError::default())?,
-                        );
-                    }
-                    let globset = globset.build().unwrap();
-
-                    for included_file in content.files.iter().filter(|f| globset.is_match(f)) {
-                        let included_file_relative = Path::new(&included_file)
-                            .strip_prefix(ex_pack.path)
-                            .unwrap();
+                    for included_file in ex_pack.included_files()? {
+                        let included_file_relative =
+                            included_file.strip_prefix(ex_pack.path).unwrap();
                         let included_file_dest = ex_pack_out_dir.join(included_file_relative);
                         let include_file_dest_dir = included_file_dest.parent().unwrap();
                         include_file_dest_dir.create_dir_all()?;
-                        included_file.copy(included_file_dest)?;
+                        report.copy(included_file, included_file_dest)?;
                     }
 
                     let ex_pack_out_dir = ex_pack_out_dir
                         .strip_prefix(output_dir)
                         .unwrap()
                         .to_path_buf();
+                    let package_name = to_tag(format!(
+                        "exercise-{}-{}-{}-{}",
+                        mod_ex.index, unit_ex.index, ex_pack.index, ex_pack.name
+                    ));
+                    rendered_packages.push((ex_pack, package_name));
                     exercise_output_paths.insert(ex_pack.path.to_path_buf(), ex_pack_out_dir);
                 }
             }
         }
 
-        Ok(exercise_output_paths)
+        // Rewrite manifests only after all packages have been copied,
+        // so that path dependencies between exercises can be resolved
+        let mut package_names = HashMap::new();
+        for (ex_pack, package_name) in rendered_packages.iter() {
+            let out_dir = output_dir.join(&exercise_output_paths[ex_pack.path]);
+            if let Some(package_name) = ex_pack.rewrite_package(&opts, package_name, &out_dir)? {
+                package_names.insert(ex_pack.path, package_name);
+            }
+        }
+        for (ex_pack, _) in rendered_packages.iter() {
+            ex_pack.rewrite_path_dependencies(
+                output_dir,
+                &exercise_output_paths,
+                &package_names,
+            )?;
+        }
+
+        Ok(RenderedExercises {
+            exercise_paths: exercise_output_paths,
+            module_paths: module_output_paths,
+            report,
+        })
     }
 }
 
@@ -126,6 +182,359 @@ pub struct ExercisePackage<'track> {
     name: &'track str,
     path: &'track Path,
     includes: &'track [String],
+    excludes: &'track [String],
+    gitignore: bool,
+    hidden_tests: &'track [PathBuf],
+}
+
+const MODMOD_IGNORE_FILE: &str = ".modmodignore";
+
+impl<'track> ExercisePackage<'track> {
+    /// Collect the files in the exercise crate that should be copied into the
+    /// exercise package. A file is included if it matches any of the
+    /// include globs, and none of the exclude globs. Include globs starting
+    /// with `!` are treated as exclude globs. Files ignored by a
+    /// `.modmodignore` file, or a `.gitignore` file if enabled, are skipped,
+    /// and so are hidden tests.
+    fn included_files(&self) -> Result<Vec<PathBuf>, RenderExercisesError> {
+        let mut includes = globset::GlobSetBuilder::new();
+        let mut excludes = globset::GlobSetBuilder::new();
+        let negated_includes = self
+            .includes
+            .iter()
+            .filter_map(|include| include.strip_prefix('!'));
+        for include in self.includes.iter().filter(|i| !i.starts_with('!')) {
+            includes.add(parse_glob(include)?);
+        }
+        for exclude in self
+            .excludes
+            .iter()
+            .map(String::as_str)
+            .chain(negated_includes)
+        {
+            excludes.add(parse_glob(exclude)?);
+        }
+        let includes = includes
+            .build()
+            .into_report()
+            .change_context(RenderExercisesError::default())?;
+        let excludes = excludes
+            .build()
+            .into_report()
+            .change_context(RenderExercisesError::default())?;
+
+        let mut files = vec![];
+        let walk = ignore::WalkBuilder::new(self.path)
+            .standard_filters(false)
+            .git_ignore(self.gitignore)
+            .require_git(false)
+            .add_custom_ignore_filename(MODMOD_IGNORE_FILE)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+        for entry in walk {
+            let entry = entry
+                .into_report()
+                .attach_printable_lazy(|| {
+                    format!(
+                        "Error walking exercise directory {}",
+                        self.path.to_string_lossy()
+                    )
+                })
+                .change_context(RenderExercisesError::default())?;
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let relative_path = entry.path().strip_prefix(self.path).unwrap();
+            if includes.is_match(relative_path)
+                && !excludes.is_match(relative_path)
+                && !self.hidden_tests.iter().any(|t| t == relative_path)
+            {
+                files.push(entry.into_path());
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+impl<'track> ExercisePackage<'track> {
+    /// Set a unique package name and the track-wide edition and rust version in the
+    /// `Cargo.toml` of a rendered exercise package. Returns the new name of the package,
+    /// if the manifest defines one.
+    fn rewrite_package(
+        &self,
+        opts: &ExerciseRenderOptions,
+        package_name: &str,
+        out_dir: &Path,
+    ) -> Result<Option<String>, RenderExercisesError> {
+        let manifest_path = out_dir.join("Cargo.toml");
+        if !manifest_path.exists() {
+            return Ok(None);
+        }
+        let mut manifest = parse_toml_document(&manifest_path)?;
+
+        let Some(original_name) = manifest
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str())
+            .map(String::from)
+        else {
+            return Ok(None);
+        };
+
+        let package = &mut manifest["package"];
+        package["name"] = toml_edit::value(package_name);
+        if let Some(edition) = opts.edition {
+            package["edition"] = toml_edit::value(edition);
+        }
+        if let Some(rust_version) = opts.rust_version {
+            package["rust-version"] = toml_edit::value(rust_version);
+        }
+
+        // Keep the library name, so that tests and examples can still refer to it
+        let has_lib = manifest.contains_key("lib") || out_dir.join("src/lib.rs").exists();
+        if has_lib && manifest.get("lib").and_then(|l| l.get("name")).is_none() {
+            let lib = manifest.entry("lib").or_insert(toml_edit::table());
+            lib["name"] = toml_edit::value(original_name.replace('-', "_"));
+        }
+
+        manifest_path
+            .create_file()?
+            .write_all(manifest.to_string())?;
+
+        rename_locked_package(&out_dir.join("Cargo.lock"), &original_name, package_name)?;
+
+        Ok(Some(package_name.to_string()))
+    }
+
+    /// Rewrite the path dependencies in the `Cargo.toml` of a rendered exercise package,
+    /// so that they point to the corresponding location in the output directory.
+    fn rewrite_path_dependencies(
+        &self,
+        output_dir: &Path,
+        exercise_output_paths: &HashMap<PathBuf, PathBuf>,
+        package_names: &HashMap<&Path, String>,
+    ) -> Result<(), RenderExercisesError> {
+        let manifest_path = output_dir
+            .join(&exercise_output_paths[self.path])
+            .join("Cargo.toml");
+        if !manifest_path.exists() {
+            return Ok(());
+        }
+        let mut manifest = parse_toml_document(&manifest_path)?;
+        let mut renamed = vec![];
+
+        let mut dependency_tables: Vec<&mut toml_edit::Item> = vec![];
+        let (root_tables, target_tables): (Vec<_>, Vec<_>) = manifest
+            .as_table_mut()
+            .iter_mut()
+            .filter(|(key, _)| DEPENDENCY_TABLES.contains(&key.get()) || key.get() == "target")
+            .partition(|(key, _)| key.get() != "target");
+        dependency_tables.extend(root_tables.into_iter().map(|(_, item)| item));
+        for (_, targets) in target_tables {
+            let Some(targets) = targets.as_table_like_mut() else {
+                continue;
+            };
+            for (_, target) in targets.iter_mut() {
+                let Some(target) = target.as_table_like_mut() else {
+                    continue;
+                };
+                dependency_tables.extend(
+                    target
+                        .iter_mut()
+                        .filter(|(key, _)| DEPENDENCY_TABLES.contains(&key.get()))
+                        .map(|(_, item)| item),
+                );
+            }
+        }
+
+        for dependencies in dependency_tables {
+            let Some(dependencies) = dependencies.as_table_like_mut() else {
+                continue;
+            };
+            for (name, dependency) in dependencies.iter_mut() {
+                let Some(dependency) = dependency.as_table_like_mut() else {
+                    continue;
+                };
+                let Some(path) = dependency.get("path").and_then(|p| p.as_str()) else {
+                    continue;
+                };
+                if Path::new(path).is_absolute() {
+                    continue;
+                }
+                let source = self
+                    .path
+                    .join(path)
+                    .canonicalize()
+                    .into_report()
+                    .attach_printable_lazy(|| format!("Path dependency {path} does not exist"))
+                    .change_context(RenderExercisesError::default())?;
+                let path = self
+                    .output_dependency_path(&source, output_dir, exercise_output_paths)
+                    .attach_printable_lazy(|| {
+                        format!(
+                            "Unable to rewrite path of dependency '{name}' of exercise at {}",
+                            self.path.to_string_lossy()
+                        )
+                    })?;
+                dependency.insert(
+                    "path",
+                    toml_edit::value(path.to_string_lossy().replace('\\', "/")),
+                );
+                // Refer to renamed exercise packages by their new name
+                if let Some(package_name) = package_names.get(source.as_path()) {
+                    let original_name = dependency
+                        .get("package")
+                        .and_then(|p| p.as_str())
+                        .unwrap_or(name.get())
+                        .to_string();
+                    dependency.insert("package", toml_edit::value(package_name));
+                    renamed.push((original_name, package_name.clone()));
+                }
+            }
+        }
+
+        manifest_path
+            .create_file()?
+            .write_all(manifest.to_string())?;
+
+        let lock_path = manifest_path.with_file_name("Cargo.lock");
+        for (original_name, package_name) in renamed {
+            rename_locked_package(&lock_path, &original_name, &package_name)?;
+        }
+
+        Ok(())
+    }
+
+    /// Determine the path of a path dependency of this exercise, given its canonical
+    /// source path, relative to the exercise's output directory. Dependencies must be
+    /// rendered as part of an exercise, and point to the rendered copy. Other dependencies,
+    /// like shared helper crates, are an error, as they're not shipped to students.
+    fn output_dependency_path(
+        &self,
+        source: &Path,
+        output_dir: &Path,
+        exercise_output_paths: &HashMap<PathBuf, PathBuf>,
+    ) -> Result<PathBuf, RenderExercisesError> {
+        let exercise_out_dir = &exercise_output_paths[self.path];
+
+        // Find the exercise the dependency is part of
+        let rendered_target = exercise_output_paths
+            .iter()
+            .filter(|(exercise_source, _)| source.starts_with(exercise_source))
+            .max_by_key(|(exercise_source, _)| exercise_source.components().count())
+            .map(|(exercise_source, dependency_out_dir)| {
+                dependency_out_dir.join(source.strip_prefix(exercise_source).unwrap())
+            })
+            .filter(|target| output_dir.join(target).exists());
+        match rendered_target {
+            Some(target) => Ok(relative_path(exercise_out_dir, &target)),
+            None => Err(error_stack::Report::new(RenderExercisesError::default()).attach_printable(format!(
+                "Path dependency {} is not part of any exercise of the track, so it would be missing from the rendered exercises. Move it into the exercise folder, or make it an exercise itself",
+                source.to_string_lossy()
+            ))),
+        }
+    }
+}
+
+/// Rename a local package in a `Cargo.lock`, both its own entry and the
+/// entries of packages that depend on it
+fn rename_locked_package(
+    lock_path: &Path,
+    original_name: &str,
+    package_name: &str,
+) -> Result<(), RenderExercisesError> {
+    if !lock_path.exists() || original_name == package_name {
+        return Ok(());
+    }
+    let mut lock = parse_toml_document(lock_path)?;
+    if let Some(packages) = lock
+        .get_mut("package")
+        .and_then(|p| p.as_array_of_tables_mut())
+    {
+        for package in packages.iter_mut() {
+            if !package.contains_key("source")
+                && package.get("name").and_then(|n| n.as_str()) == Some(original_name)
+            {
+                package["name"] = toml_edit::value(package_name);
+            }
+            let Some(dependencies) = package
+                .get_mut("dependencies")
+                .and_then(|d| d.as_array_mut())
+            else {
+                continue;
+            };
+            for dependency in dependencies.iter_mut() {
+                if let Some(renamed) = dependency
+                    .as_str()
+                    .and_then(|d| rename_lock_dependency(d, original_name, package_name))
+                {
+                    let decor = dependency.decor().clone();
+                    *dependency = renamed.into();
+                    *dependency.decor_mut() = decor;
+                }
+            }
+        }
+    }
+    lock_path.create_file()?.write_all(lock.to_string())?;
+    Ok(())
+}
+
+/// Rename a `dependencies` entry of a `Cargo.lock` package, which is formatted like
+/// `name`, `name version` or `name version (source)`. Entries with a source refer to
+/// registry or git packages, which are never renamed.
+fn rename_lock_dependency(entry: &str, original_name: &str, package_name: &str) -> Option<String> {
+    let (name, rest) = entry.split_once(' ').unwrap_or((entry, ""));
+    if name != original_name || rest.contains('(') {
+        return None;
+    }
+    Some(if rest.is_empty() {
+        package_name.to_string()
+    } else {
+        format!("{package_name} {rest}")
+    })
+}
+
+/// Compute the relative path from directory `from` to `to`,
+/// both of which are relative to the same base directory.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let path: PathBuf = from
+        .components()
+        .skip(common)
+        .map(|_| Path::new(".."))
+        .chain(
+            to.components()
+                .skip(common)
+                .map(|c| Path::new(c.as_os_str())),
+        )
+        .collect();
+    if path.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        path
+    }
+}
+
+fn parse_toml_document(path: &Path) -> Result<toml_edit::Document, RenderExercisesError> {
+    path.read_to_string::<RenderExercisesError>()?
+        .parse::<toml_edit::Document>()
+        .into_report()
+        .attach_printable_lazy(|| format!("Error parsing {}", path.to_string_lossy()))
+        .change_context(RenderExercisesError::default())
+}
+
+fn parse_glob(glob: &str) -> Result<globset::Glob, RenderExercisesError> {
+    globset::Glob::new(glob)
+        .into_report()
+        .attach_printable_lazy(|| format!("Error parsing glob '{glob}'"))
+        .change_context(RenderExercisesError::default())
 }
 
 pub struct ExerciseCollectionBuilder<'track> {
@@ -189,13 +598,24 @@ pub struct UnitExercisesBuilder<'track, 'c, 'm> {
 }
 
 impl<'track, 'c, 'm> UnitExercisesBuilder<'track, 'c, 'm> {
-    pub fn package(&mut self, name: &'track str, path: &'track Path, includes: &'track [String]) {
+    pub fn package(
+        &mut self,
+        name: &'track str,
+        path: &'track Path,
+        includes: &'track [String],
+        excludes: &'track [String],
+        gitignore: bool,
+        hidden_tests: &'track [PathBuf],
+    ) {
         let index = self.unit_exercises.exercises.len() + 1;
         self.unit_exercises.exercises.push(ExercisePackage {
             index,
             name,
             path,
             includes,
+            excludes,
+            gitignore,
+            hidden_tests,
         })
     }
 
@@ -207,3 +627,125 @@ impl<'track, 'c, 'm> UnitExercisesBuilder<'track, 'c, 'm> {
         self.module_builder
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_path_between_exercise_dirs() {
+        assert_eq!(
+            relative_path(
+                Path::new("exercises/1-a/1-b/2-c"),
+                Path::new("exercises/1-a/2-d/1-e/helper")
+            ),
+            Path::new("../../2-d/1-e/helper")
+        );
+        assert_eq!(
+            relative_path(Path::new("exercises/1-a"), Path::new("exercises/1-a")),
+            Path::new(".")
+        );
+    }
+
+    #[test]
+    fn rename_lock_dependency_skips_other_sources() {
+        assert_eq!(
+            rename_lock_dependency("helper", "helper", "1-a-helper").as_deref(),
+            Some("1-a-helper")
+        );
+        assert_eq!(
+            rename_lock_dependency("helper 0.1.0", "helper", "1-a-helper").as_deref(),
+            Some("1-a-helper 0.1.0")
+        );
+        assert_eq!(
+            rename_lock_dependency(
+                "helper 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                "helper",
+                "1-a-helper"
+            ),
+            None
+        );
+        assert_eq!(
+            rename_lock_dependency("serde", "helper", "1-a-helper"),
+            None
+        );
+    }
+
+    #[test]
+    fn included_files_applies_globs_and_ignore_files() {
+        let dir = crate::io::test_dir("included-files");
+        for file in [
+            "Cargo.toml",
+            "src/main.rs",
+            "src/main.rs~",
+            "src/solution.rs",
+            "src/notes.md",
+            "tests/hidden.rs",
+            "target/debug/build.log",
+            "README.md",
+        ] {
+            crate::io::write_test_file(dir.join(file), "");
+        }
+        crate::io::write_test_file(dir.join(".modmodignore"), "solution.rs\n");
+
+        let includes = [
+            crate::load::serde_defaults::exercise_includes(),
+            vec!["tests/*.rs".into(), "!**/*.md".into()],
+        ]
+        .concat();
+        let excludes = crate::load::serde_defaults::exercise_excludes();
+        let hidden_tests = [PathBuf::from("tests/hidden.rs")];
+        let package = ExercisePackage {
+            index: 1,
+            name: "ex",
+            path: &dir,
+            includes: &includes,
+            excludes: &excludes,
+            gitignore: false,
+            hidden_tests: &hidden_tests,
+        };
+
+        let files: Vec<_> = package
+            .included_files()
+            .unwrap()
+            .into_iter()
+            .map(|f| f.strip_prefix(&dir).unwrap().to_owned())
+            .collect();
+        assert_eq!(
+            files,
+            [PathBuf::from("Cargo.toml"), PathBuf::from("src/main.rs")]
+        );
+    }
+
+    #[test]
+    fn path_dependencies_must_be_rendered() {
+        let dir = crate::io::test_dir("dependency-paths");
+        crate::io::write_test_file(dir.join("content/ex/bindgen/Cargo.toml"), "");
+        crate::io::write_test_file(dir.join("content/helper/Cargo.toml"), "");
+        crate::io::write_test_file(dir.join("out/exercises/1-a/1-ex/bindgen/Cargo.toml"), "");
+        let exercise_path = dir.join("content/ex").canonicalize().unwrap();
+        let exercise_output_paths =
+            HashMap::from([(exercise_path.clone(), PathBuf::from("exercises/1-a/1-ex"))]);
+        let package = ExercisePackage {
+            index: 1,
+            name: "ex",
+            path: &exercise_path,
+            includes: &[],
+            excludes: &[],
+            gitignore: false,
+            hidden_tests: &[],
+        };
+
+        let bindgen = exercise_path.join("bindgen");
+        assert_eq!(
+            package
+                .output_dependency_path(&bindgen, &dir.join("out"), &exercise_output_paths)
+                .unwrap(),
+            Path::new("bindgen")
+        );
+        let helper = dir.join("content/helper").canonicalize().unwrap();
+        assert!(package
+            .output_dependency_path(&helper, &dir.join("out"), &exercise_output_paths)
+            .is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    ffi::OsStr,
+    fmt,
+    path::Path,
+    process::{Command, Output},
+};
+
+use error_stack::{Report, Result};
+
+use crate::io::run_command;
+
+#[non_exhaustive]
+#[derive(Debug, Default)]
+pub struct GitReleaseError;
+
+impl fmt::Display for GitReleaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to publish exercises to git repository")
+    }
+}
+
+impl error_stack::Context for GitReleaseError {}
+
+pub struct GitReleaseOptions<'a, E: AsRef<Path>, R: AsRef<Path>> {
+    /// The folder containing the rendered exercises
+    pub exercises_dir: E,
+    /// The local git repository to publish the exercises to.
+    /// A bare repository is created if it doesn't exist yet.
+    pub repo: R,
+    /// The branch to commit the exercises to
+    pub branch: &'a str,
+    /// The tag to create for the release
+    pub tag: &'a str,
+    /// The commit and tag message
+    pub message: &'a str,
+}
+
+/// Publishes rendered exercises as a commit on a release branch in a local git repository,
+/// so that students can pull updates instead of applying patches.
+#[non_exhaustive]
+pub struct GitRelease {}
+
+impl GitRelease {
+    /// Commit the contents of the exercises folder onto the release branch, and tag the commit.
+    /// The commit is created using git plumbing commands, leaving the working tree
+    /// and index of the repository untouched.
+    pub fn render<E: AsRef<Path>, R: AsRef<Path>>(
+        GitReleaseOptions {
+            exercises_dir,
+            repo,
+            branch,
+            tag,
+            message,
+        }: GitReleaseOptions<'_, E, R>,
+    ) -> Result<(), GitReleaseError> {
+        let exercises_dir = exercises_dir.as_ref();
+        let repo = repo.as_ref();
+
+        if !repo.exists() {
+            run_command(
+                Command::new("git")
+                    .args(["init", "--bare", "--quiet"])
+                    .arg(repo),
+            )?;
+        }
+
+        let git_dir = String::from_utf8_lossy(
+            &run_command(
+                Command::new("git")
+                    .arg("-C")
+                    .arg(repo)
+                    .args(["rev-parse", "--absolute-git-dir"]),
+            )?
+            .stdout,
+        )
+        .trim()
+        .to_string();
+        let git_dir = Path::new(&git_dir);
+
+        let branch_ref = format!("refs/heads/{branch}");
+        let is_bare = git(git_dir, ["rev-parse", "--is-bare-repository"])?.stdout;
+        let head = git(git_dir, ["symbolic-ref", "--quiet", "HEAD"]).ok();
+        if String::from_utf8_lossy(&is_bare).trim() == "false"
+            && head.is_some_and(|h| String::from_utf8_lossy(&h.stdout).trim() == branch_ref)
+        {
+            return Err(Report::new(GitReleaseError).attach_printable(format!(
+                "Branch {branch} is checked out in {repo}. Check out another branch first.",
+                repo = repo.to_string_lossy()
+            )));
+        }
+
+        if git(
+            git_dir,
+            [
+                "rev-parse",
+                "--quiet",
+                "--verify",
+                &format!("refs/tags/{tag}"),
+            ],
+        )
+        .is_ok()
+        {
+            return Err(
+                Report::new(GitReleaseError).attach_printable(format!("Tag {tag} already exists"))
+            );
+        }
+
+        // Stage the exercises using a temporary index, to create a tree object
+        let index_file = git_dir.join("modmod-index");
+        let tree = run_command(
+            Command::new("git")
+                .arg("--git-dir")
+                .arg(git_dir)
+                .arg("--work-tree")
+                .arg(exercises_dir)
+                .env("GIT_INDEX_FILE", &index_file)
+                .args(["add", "--all", "--force", "."]),
+        )
+        .and_then(|_| {
+            run_command(
+                Command::new("git")
+                    .arg("--git-dir")
+                    .arg(git_dir)
+                    .env("GIT_INDEX_FILE", &index_file)
+                    .arg("write-tree"),
+            )
+        });
+        std::fs::remove_file(&index_file).ok();
+        let tree = String::from_utf8_lossy(&tree?.stdout).trim().to_string();
+
+        let parent = git(git_dir, ["rev-parse", "--quiet", "--verify", &branch_ref])
+            .ok()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+        let parent_tree = parent.as_ref().and_then(|parent| {
+            git(git_dir, ["rev-parse", &format!("{parent}^{{tree}}")])
+                .ok()
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        });
+
+        // Only create a new commit if the exercises changed since the last release
+        let commit = match (parent, parent_tree) {
+            (Some(parent), Some(parent_tree)) if parent_tree == tree => parent,
+            (parent, _) => {
+                let mut args = identity_args(git_dir);
+                args.extend(["commit-tree", &tree, "-m", message].map(String::from));
+                if let Some(parent) = parent {
+                    args.extend(["-p".to_string(), parent]);
+                }
+                let commit = git(git_dir, args)?;
+                let commit = String::from_utf8_lossy(&commit.stdout).trim().to_string();
+                git(git_dir, ["update-ref", &branch_ref, &commit])?;
+                commit
+            }
+        };
+
+        let mut args = identity_args(git_dir);
+        args.extend(["tag", "--annotate", "-m", message, tag, &commit].map(String::from));
+        git(git_dir, args)?;
+
+        Ok(())
+    }
+}
+
+/// Configuration arguments that set a fallback committer identity, for machines
+/// without a configured `user.name` or `user.email`
+fn identity_args(git_dir: &Path) -> Vec<String> {
+    [("user.name", "modmod"), ("user.email", "modmod@localhost")]
+        .into_iter()
+        .filter(|(key, _)| git(git_dir, ["config", "--get", key]).is_err())
+        .flat_map(|(key, value)| ["-c".to_string(), format!("{key}={value}")])
+        .collect()
+}
+
+fn git<I, S>(git_dir: &Path, args: I) -> Result<Output, GitReleaseError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    run_command(Command::new("git").arg("--git-dir").arg(git_dir).args(args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{test_dir, write_test_file};
+
+    fn git_output(repo: &Path, args: &[&str]) -> String {
+        String::from_utf8_lossy(&git(repo, args).unwrap().stdout)
+            .trim()
+            .to_string()
+    }
+
+    #[test]
+    fn release_commits_exercises_and_tags() {
+        let dir = test_dir("git-release");
+        let exercises_dir = dir.join("exercises");
+        let repo = dir.join("release.git");
+        write_test_file(exercises_dir.join("1-intro/src/main.rs"), "fn main() {}\n");
+        let release = |tag| {
+            GitRelease::render(GitReleaseOptions {
+                exercises_dir: &exercises_dir,
+                repo: &repo,
+                branch: "release",
+                tag,
+                message: "Release",
+            })
+        };
+
+        release("v1").unwrap();
+        assert_eq!(
+            git_output(&repo, &["ls-tree", "-r", "--name-only", "release"]),
+            "1-intro/src/main.rs"
+        );
+        assert_eq!(
+            git_output(&repo, &["rev-parse", "v1^{commit}"]),
+            git_output(&repo, &["rev-parse", "release"])
+        );
+
+        // Unchanged exercises are tagged without a new commit
+        release("v2").unwrap();
+        assert_eq!(
+            git_output(&repo, &["rev-parse", "v2^{commit}"]),
+            git_output(&repo, &["rev-parse", "v1^{commit}"])
+        );
+
+        write_test_file(exercises_dir.join("1-intro/src/lib.rs"), "");
+        release("v3").unwrap();
+        assert_eq!(
+            git_output(&repo, &["rev-parse", "release^"]),
+            git_output(&repo, &["rev-parse", "v1^{commit}"])
+        );
+
+        assert!(release("v3").is_err());
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    fmt::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::Serialize;
+
+use crate::{
+    cargo::{run_test_targets, TestCase, TestCaseResult, TestRunOptions},
+    io::{escape_xml, PathExt, WriteExt},
+    progress::{find_exercise_dir, student_exercises_dir, ExerciseStatus},
+    Track,
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct GradeError;
+
+impl fmt::Display for GradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to grade exercises")
+    }
+}
+
+impl error_stack::Context for GradeError {}
+
+pub struct GradeOptions<'a, S: AsRef<Path>, W: AsRef<Path>> {
+    /// The student's exercises folder
+    pub student_dir: S,
+    /// The folder the submissions are copied into before the hidden tests are added
+    pub work_dir: W,
+    /// Only grade the exercises with these references, for example `2.3.1`, or names.
+    /// If empty, all exercises that declare hidden tests are graded.
+    /// Selecting an exercise without hidden tests is an error.
+    pub exercises: &'a [String],
+    pub timeout: std::time::Duration,
+    /// Called with the reference of each exercise before it is graded,
+    /// for instance to show progress
+    pub on_grade: Option<&'a dyn Fn(&str)>,
+}
+
+/// The grades of a student's submissions
+#[derive(Debug, Serialize)]
+pub struct GradeReport<'track> {
+    pub track: &'track str,
+    pub exercises: Vec<ExerciseGrade<'track>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExerciseGrade<'track> {
+    /// Reference of the exercise, as used in the exercise book. For instance, `2.3.1`
+    pub reference: String,
+    pub name: &'track str,
+    /// The path of the submission in the student's exercises folder
+    pub path: Option<PathBuf>,
+    pub status: ExerciseStatus,
+    /// The fraction of tests that passed, between 0 and 1.
+    /// Zero if the submission is missing, doesn't build or times out.
+    pub score: f64,
+    pub tests: Vec<TestCase>,
+    #[serde(skip)]
+    pub output: String,
+}
+
+impl<'track> GradeReport<'track> {
+    /// Grade a student's submissions. Each submission is copied into the work folder,
+    /// its build configuration is replaced by that of the exercise, the hidden tests of the
+    /// exercise are added, and the hidden tests are run offline.
+    pub fn create<S: AsRef<Path>, W: AsRef<Path>>(
+        track: &'track Track,
+        GradeOptions {
+            student_dir,
+            work_dir,
+            exercises: selected,
+            timeout,
+            on_grade,
+        }: GradeOptions<'_, S, W>,
+    ) -> Result<Self, GradeError> {
+        let exercises_dir = student_exercises_dir(student_dir.as_ref());
+        let work_dir = work_dir.as_ref();
+        let target_dir = work_dir.join("target");
+
+        let mut exercises = vec![];
+        for module in track.modules.iter() {
+            for unit in module.data.units.iter() {
+                for (exercise, exercise_index) in unit.data.numbered_exercises() {
+                    let reference = format!("{}.{}.{exercise_index}", module.index, unit.index);
+                    let is_selected = if selected.is_empty() {
+                        !exercise.data.hidden_tests.is_empty()
+                    } else {
+                        selected
+                            .iter()
+                            .any(|s| *s == reference || *s == exercise.data.name)
+                    };
+                    if !is_selected {
+                        continue;
+                    }
+                    if exercise.data.hidden_tests.is_empty() {
+                        return Err(error_stack::Report::new(GradeError).attach_printable(
+                            format!(
+                                "Exercise {reference} has no hidden tests, so it can't be graded"
+                            ),
+                        ));
+                    }
+
+                    let path =
+                        find_exercise_dir(&exercises_dir, module.index, unit.index, exercise_index)
+                            .change_context(GradeError)?;
+                    let mut grade = ExerciseGrade {
+                        reference,
+                        name: &exercise.data.name,
+                        path: path.clone(),
+                        status: ExerciseStatus::Missing,
+                        score: 0.,
+                        tests: vec![],
+                        output: String::new(),
+                    };
+
+                    if let Some(path) = path {
+                        if let Some(on_grade) = on_grade {
+                            on_grade(&grade.reference);
+                        }
+                        let submission_dir = work_dir.join(&grade.reference);
+                        if submission_dir.exists() {
+                            std::fs::remove_dir_all(&submission_dir)
+                                .into_report()
+                                .change_context(GradeError)?;
+                        }
+                        copy_submission(&path, &submission_dir)?;
+                        restore_build_files(
+                            &exercise.data.path,
+                            &submission_dir,
+                            track.edition.as_deref(),
+                        )?;
+                        for test in exercise.data.hidden_tests.iter() {
+                            let dest = submission_dir.join(test);
+                            dest.parent().unwrap().create_dir_all()?;
+                            exercise.data.path.join(test).copy(dest)?;
+                        }
+
+                        // Only the hidden tests count towards the grade, not the student's own tests
+                        let test_opts = TestRunOptions {
+                            timeout,
+                            offline: true,
+                            // Share build artifacts of dependencies between submissions
+                            target_dir: Some(target_dir.clone()),
+                        };
+                        let test_targets = hidden_test_targets(&exercise.data.hidden_tests)?;
+                        let test_run = run_test_targets(&submission_dir, &test_targets, &test_opts)
+                            .into_report()
+                            .attach_printable_lazy(|| {
+                                format!(
+                                    "Unable to run tests in {}",
+                                    submission_dir.to_string_lossy()
+                                )
+                            })
+                            .change_context(GradeError)?;
+                        grade.status = test_run.outcome.into();
+                        grade.score = match grade.status {
+                            ExerciseStatus::Passed | ExerciseStatus::Failed
+                                if test_run.passed + test_run.failed > 0 =>
+                            {
+                                test_run.passed as f64 / (test_run.passed + test_run.failed) as f64
+                            }
+                            ExerciseStatus::Passed => 1.,
+                            _ => 0.,
+                        };
+                        grade.tests = test_run.tests;
+                        grade.output = test_run.output;
+                    }
+
+                    exercises.push(grade);
+                }
+            }
+        }
+
+        Ok(GradeReport {
+            track: &track.name,
+            exercises,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Render the report as JUnit XML, with a test suite per exercise
+    pub fn to_junit_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let count =
+            |e: &ExerciseGrade, result| e.tests.iter().filter(|t| t.result == result).count();
+        let has_error = |e: &ExerciseGrade| {
+            !matches!(e.status, ExerciseStatus::Passed | ExerciseStatus::Failed)
+        };
+        let (tests, failures, errors) =
+            self.exercises
+                .iter()
+                .fold((0, 0, 0), |(tests, failures, errors), e| {
+                    (
+                        tests + e.tests.len() + usize::from(has_error(e)),
+                        failures + count(e, TestCaseResult::Failed),
+                        errors + usize::from(has_error(e)),
+                    )
+                });
+        writeln!(
+            xml,
+            r#"<testsuites name="{}" tests="{tests}" failures="{failures}" errors="{errors}">"#,
+            escape_xml(self.track)
+        )
+        .unwrap();
+
+        for exercise in self.exercises.iter() {
+            let suite_name = escape_xml(&format!("{} {}", exercise.reference, exercise.name));
+            writeln!(
+                xml,
+                r#"  <testsuite name="{suite_name}" tests="{}" failures="{}" errors="{}" skipped="{}">"#,
+                exercise.tests.len() + usize::from(has_error(exercise)),
+                count(exercise, TestCaseResult::Failed),
+                usize::from(has_error(exercise)),
+                count(exercise, TestCaseResult::Ignored),
+            )
+            .unwrap();
+            for test in exercise.tests.iter() {
+                let name = escape_xml(&test.name);
+                match test.result {
+                    TestCaseResult::Ok => {
+                        writeln!(xml, r#"    <testcase name="{name}" classname="{suite_name}"/>"#)
+                    }
+                    TestCaseResult::Failed => writeln!(
+                        xml,
+                        r#"    <testcase name="{name}" classname="{suite_name}"><failure message="test failed"/></testcase>"#
+                    ),
+                    TestCaseResult::Ignored => writeln!(
+                        xml,
+                        r#"    <testcase name="{name}" classname="{suite_name}"><skipped/></testcase>"#
+                    ),
+                }
+                .unwrap();
+            }
+            if has_error(exercise) {
+                writeln!(
+                    xml,
+                    r#"    <testcase name="cargo test" classname="{suite_name}"><error message="{}"/></testcase>"#,
+                    exercise.status
+                )
+                .unwrap();
+            }
+            writeln!(
+                xml,
+                "    <system-out>{}</system-out>\n  </testsuite>",
+                escape_xml(&exercise.output)
+            )
+            .unwrap();
+        }
+
+        xml += "</testsuites>\n";
+        xml
+    }
+}
+
+/// The names of the integration test targets of hidden test files,
+/// which must be directly in the `tests` folder of the exercise crate
+fn hidden_test_targets(hidden_tests: &[PathBuf]) -> Result<Vec<String>, GradeError> {
+    hidden_tests
+        .iter()
+        .map(|test| {
+            let is_integration_test = test.parent() == Some(Path::new("tests"))
+                && test.extension().is_some_and(|e| e == "rs");
+            if !is_integration_test {
+                return Err(error_stack::Report::new(GradeError).attach_printable(format!(
+                    "Hidden test {} is not a file in the tests folder, so it can't be run on its own",
+                    test.to_string_lossy()
+                )));
+            }
+            Ok(test.file_stem().unwrap().to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// Files that decide how a package is built and tested. A student could use them to change
+/// which tests run, or to run code that rewrites the hidden tests, so they're taken from
+/// the exercise: copied from it, or removed if the exercise doesn't have them.
+const BUILD_FILES: &[&str] = &[
+    "Cargo.toml",
+    "build.rs",
+    ".cargo/config.toml",
+    ".cargo/config",
+    "rust-toolchain",
+    "rust-toolchain.toml",
+];
+
+/// Replace the build files of a submission by those of the exercise at `exercise_dir`.
+/// The `Cargo.lock` of the exercise is used too, if it has one, so that dependencies resolve
+/// offline. The edition of the track is set in the manifest, like when rendering the exercise.
+fn restore_build_files(
+    exercise_dir: &Path,
+    submission_dir: &Path,
+    edition: Option<&str>,
+) -> Result<(), GradeError> {
+    for file in BUILD_FILES {
+        let (src, dest) = (exercise_dir.join(file), submission_dir.join(file));
+        if src.exists() {
+            dest.parent().unwrap().create_dir_all()?;
+            src.copy(&dest)?;
+        } else if dest.exists() {
+            std::fs::remove_file(&dest)
+                .into_report()
+                .change_context(GradeError)?;
+        }
+    }
+    let lock_path = exercise_dir.join("Cargo.lock");
+    if lock_path.exists() {
+        lock_path.copy(submission_dir.join("Cargo.lock"))?;
+    }
+
+    let manifest_path = submission_dir.join("Cargo.toml");
+    if let (Some(edition), true) = (edition, manifest_path.exists()) {
+        let mut manifest: toml_edit::Document = manifest_path
+            .read_to_string()?
+            .parse()
+            .into_report()
+            .attach_printable_lazy(|| {
+                format!("Unable to parse {}", manifest_path.to_string_lossy())
+            })
+            .change_context(GradeError)?;
+        if let Some(package) = manifest
+            .get_mut("package")
+            .and_then(|p| p.as_table_like_mut())
+        {
+            package.insert("edition", toml_edit::value(edition));
+        }
+        manifest_path
+            .create_file()?
+            .write_all(manifest.to_string())?;
+    }
+    Ok(())
+}
+
+/// Copy a submission into `dest`, leaving out build artifacts
+fn copy_submission(src: &Path, dest: &Path) -> Result<(), GradeError> {
+    let walk = ignore::WalkBuilder::new(src)
+        .standard_filters(false)
+        .filter_entry(|e| e.file_name() != "target")
+        .build();
+    for entry in walk {
+        let entry = entry.into_report().change_context(GradeError)?;
+        let relative_path = entry.path().strip_prefix(src).unwrap();
+        if entry.path().is_dir() {
+            dest.join(relative_path).create_dir_all()?;
+        } else {
+            entry.path().copy(dest.join(relative_path))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hidden_test_targets_must_be_integration_tests() {
+        let targets = hidden_test_targets(&[
+            PathBuf::from("tests/grading.rs"),
+            PathBuf::from("tests/b.rs"),
+        ])
+        .unwrap();
+        assert_eq!(targets, ["grading", "b"]);
+        assert!(hidden_test_targets(&[PathBuf::from("src/grading.rs")]).is_err());
+        assert!(hidden_test_targets(&[PathBuf::from("tests/grading/main.rs")]).is_err());
+    }
+
+    #[test]
+    fn restore_build_files_replaces_student_configuration() {
+        let dir = crate::io::test_dir("restore-build-files");
+        let (exercise, submission) = (dir.join("exercise"), dir.join("submission"));
+        crate::io::write_test_file(
+            exercise.join("Cargo.toml"),
+            "[package]\nname = \"ex\"\nedition = \"2018\"\n",
+        );
+        crate::io::write_test_file(
+            submission.join("Cargo.toml"),
+            "[package]\nname = \"ex\"\n\n[[test]]\nname = \"grading\"\npath = \"src/fake.rs\"\n",
+        );
+        crate::io::write_test_file(submission.join("build.rs"), "fn main() {}");
+        crate::io::write_test_file(submission.join(".cargo/config.toml"), "");
+        crate::io::write_test_file(submission.join("src/lib.rs"), "");
+
+        restore_build_files(&exercise, &submission, Some("2021")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(submission.join("Cargo.toml")).unwrap(),
+            "[package]\nname = \"ex\"\nedition = \"2021\"\n"
+        );
+        assert!(!submission.join("build.rs").exists());
+        assert!(!submission.join(".cargo/config.toml").exists());
+        assert!(submission.join("src/lib.rs").exists());
+    }
+}
@@ -0,0 +1,471 @@
+use std::{fmt, fmt::Write as _, path::Path};
+
+use error_stack::{Result, ResultExt};
+
+use crate::{
+    io::{PathExt, WriteExt},
+    load::Question,
+    slug::{to_prefixed_tag, SlugRegistry},
+    variables::substitute_variables,
+    RenderReport, Track, Unit,
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct RenderGuideError;
+
+impl fmt::Display for RenderGuideError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to render instructor guide")
+    }
+}
+
+impl error_stack::Context for RenderGuideError {}
+
+/// The speaker notes of a slide
+#[derive(Debug, PartialEq, Eq)]
+struct SlideNotes<'c> {
+    /// The first heading of the slide, if any
+    title: Option<&'c str>,
+    notes: String,
+}
+
+/// Render an mdBook for instructors into `out_dir/instructor-guide`, with the objectives,
+/// estimated duration, speaker notes and exercises of every topic, and the quiz answers.
+pub(crate) fn render_instructor_guide(
+    track: &Track,
+    out_dir: impl AsRef<Path>,
+    slugs: &mut SlugRegistry,
+) -> Result<RenderReport, RenderGuideError> {
+    let mut report = RenderReport::default();
+    let guide_out_dir = out_dir.as_ref().join("instructor-guide");
+    let guide_src_dir = guide_out_dir.join("src");
+    guide_src_dir.create_dir_all()?;
+
+    let mut book = toml_edit::Table::new();
+    book["title"] = toml_edit::value(format!("{} - Instructor guide", track.name));
+    if !track.metadata.authors.is_empty() {
+        book["authors"] =
+            toml_edit::value(track.metadata.authors.iter().collect::<toml_edit::Array>());
+    }
+    book["language"] = toml_edit::value("en");
+    let mut build = toml_edit::Table::new();
+    build["build-dir"] = toml_edit::value("./target");
+    let mut book_toml = toml_edit::Document::new();
+    book_toml["book"] = toml_edit::Item::Table(book);
+    book_toml["build"] = toml_edit::Item::Table(build);
+    report
+        .create_file(guide_out_dir.join("book.toml"))?
+        .write_all(book_toml.to_string())?;
+
+    let mut summary = String::from("# Summary\n\n[Overview](overview.md)\n\n");
+    let mut overview = format!(
+        "# {} - Instructor guide\n\n| Unit | Topics | Exercises | Estimated time |\n|---|---|---|---|\n",
+        track.name
+    );
+    let mut answer_key = String::new();
+    let mut coverage = String::new();
+    let (mut objective_count, mut covered_count) = (0, 0);
+
+    for module in track.modules.iter() {
+        let module_index = module.index;
+        writeln!(summary, "- [{}]()", module.data.name).unwrap();
+
+        for unit in module.data.units.iter() {
+            let unit_ref = format!("{module_index}.{}", unit.index);
+            let unit_slug = slugs
+                .claim(
+                    "instructor-guide/src",
+                    &unit.data.name,
+                    to_prefixed_tag(&unit.data.name, format!("{module_index}_{}", unit.index)),
+                )
+                .change_context(RenderGuideError)?;
+            let unit_file_name = Path::new(&unit_slug).with_extension("md");
+            writeln!(
+                summary,
+                "\t- [{}]({})",
+                unit.data.name,
+                unit_file_name.to_string_lossy()
+            )
+            .unwrap();
+
+            let topics = &unit.data.topics;
+            let unit_duration: u32 = topics.iter().filter_map(|t| t.data.duration_minutes).sum();
+            let mut page = format!("# Unit {unit_ref} - {}\n\n", unit.data.name);
+            if unit_duration > 0 {
+                writeln!(page, "Estimated time: {unit_duration} minutes\n").unwrap();
+            }
+
+            let mut numbered_exercises = unit.data.numbered_exercises();
+            let mut unit_questions = vec![];
+            for topic in topics.iter() {
+                let topic = &topic.data;
+                writeln!(page, "## {}\n", topic.name).unwrap();
+                if let Some(duration) = topic.duration_minutes {
+                    writeln!(page, "*Estimated time: {duration} minutes*\n").unwrap();
+                }
+
+                if !topic.objectives.is_empty() {
+                    page += "### Objectives\n\n";
+                    for objective in topic.objectives.iter() {
+                        writeln!(page, "- {}", objective.trim()).unwrap();
+                    }
+                    page += "\n";
+                }
+
+                let content = topic.content.read_to_string()?;
+                let notes = speaker_notes(&content);
+                if !notes.is_empty() {
+                    page += "### Speaker notes\n\n";
+                    for SlideNotes { title, notes } in notes {
+                        if let Some(title) = title {
+                            writeln!(page, "#### {title}\n").unwrap();
+                        }
+                        writeln!(page, "{notes}\n").unwrap();
+                    }
+                }
+
+                if !topic.exercises.is_empty() {
+                    page += "### Exercises\n\n";
+                    let topic_exercises = numbered_exercises.by_ref().take(topic.exercises.len());
+                    for (exercise, n) in topic_exercises {
+                        writeln!(page, "- {unit_ref}.{n}: {}", exercise.data.name).unwrap();
+                    }
+                    page += "\n";
+                }
+
+                if let Some(quiz) = &topic.quiz {
+                    unit_questions.extend(quiz.questions.iter());
+                }
+            }
+
+            let page = substitute_variables(&page, &[&module.data.variables, &track.variables])
+                .attach_printable_lazy(|| format!("Error in notes of unit {}", unit.data.name))
+                .change_context(RenderGuideError)?;
+            report
+                .create_file(guide_src_dir.join(&unit_file_name))?
+                .write_all(page.trim_end().to_string() + "\n")?;
+
+            let exercise_count = unit.data.numbered_exercises().count();
+
+            let unit_coverage = unit_coverage(&unit_ref, &unit.data);
+            objective_count += unit_coverage.objectives.len();
+            covered_count += unit_coverage
+                .objectives
+                .iter()
+                .filter(|(_, exercises)| !exercises.is_empty())
+                .count();
+            if !unit_coverage.objectives.is_empty() || !unit_coverage.uncovering.is_empty() {
+                write!(
+                    coverage,
+                    "## Unit {unit_ref} - {}\n\n{unit_coverage}\n",
+                    unit.data.name
+                )
+                .unwrap();
+            }
+            writeln!(
+                overview,
+                "| [{unit_ref} {}]({}) | {} | {exercise_count} | {} |",
+                unit.data.name,
+                unit_file_name.to_string_lossy(),
+                topics.len(),
+                if unit_duration > 0 {
+                    format!("{unit_duration} min")
+                } else {
+                    "-".to_string()
+                }
+            )
+            .unwrap();
+
+            if !unit_questions.is_empty() {
+                write!(
+                    answer_key,
+                    "## Unit {unit_ref} - {}\n\n{}\n",
+                    unit.data.name,
+                    answer_key_entries(&unit_questions)
+                )
+                .unwrap();
+            }
+        }
+        summary += "\n";
+    }
+
+    let track_duration: u32 = track
+        .modules
+        .iter()
+        .flat_map(|m| m.data.units.iter())
+        .flat_map(|u| u.data.topics.iter())
+        .filter_map(|t| t.data.duration_minutes)
+        .sum();
+    if track_duration > 0 {
+        writeln!(
+            overview,
+            "\nEstimated time in total: {track_duration} minutes"
+        )
+        .unwrap();
+    }
+    report
+        .create_file(guide_src_dir.join("overview.md"))?
+        .write_all(overview)?;
+
+    summary += "[Objective coverage](coverage.md)\n";
+    report
+        .create_file(guide_src_dir.join("coverage.md"))?
+        .write_all(format!(
+            "# Objective coverage\n\n{covered_count} of {objective_count} objectives are covered by an exercise.\n\n{coverage}"
+        ))?;
+
+    if !answer_key.is_empty() {
+        summary += "[Quiz answer key](answer-key.md)\n";
+        report
+            .create_file(guide_src_dir.join("answer-key.md"))?
+            .write_all(format!("# Quiz answer key\n\n{answer_key}"))?;
+    }
+
+    report
+        .create_file(guide_src_dir.join("SUMMARY.md"))?
+        .write_all(summary)?;
+
+    Ok(report)
+}
+
+/// Which exercises of a unit cover which objectives of its topics
+struct UnitCoverage<'t> {
+    /// The objectives of all topics, with the exercises that cover them
+    objectives: Vec<(&'t str, Vec<String>)>,
+    /// The exercises that don't cover any objective
+    uncovering: Vec<String>,
+}
+
+fn unit_coverage<'t>(unit_ref: &str, unit: &'t Unit) -> UnitCoverage<'t> {
+    let mut objectives = vec![];
+    let mut uncovering = vec![];
+    let mut numbered_exercises = unit.numbered_exercises();
+    for topic in unit.topics.iter() {
+        let exercises: Vec<_> = numbered_exercises
+            .by_ref()
+            .take(topic.data.exercises.len())
+            .map(|(exercise, n)| {
+                let label = format!("{unit_ref}.{n} {}", exercise.data.name);
+                (&exercise.data, label)
+            })
+            .collect();
+        for objective in topic.data.objectives.iter() {
+            let covering = exercises
+                .iter()
+                .filter(|(exercise, _)| {
+                    exercise.covers.iter().any(|c| c.trim() == objective.trim())
+                })
+                .map(|(_, label)| label.clone())
+                .collect();
+            objectives.push((objective.trim(), covering));
+        }
+        uncovering.extend(
+            exercises
+                .into_iter()
+                .filter(|(exercise, _)| exercise.covers.is_empty())
+                .map(|(_, label)| label),
+        );
+    }
+
+    UnitCoverage {
+        objectives,
+        uncovering,
+    }
+}
+
+impl fmt::Display for UnitCoverage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.objectives.is_empty() {
+            writeln!(f, "| Objective | Exercises |\n|---|---|")?;
+            for (objective, exercises) in self.objectives.iter() {
+                let exercises = if exercises.is_empty() {
+                    "**None**".to_string()
+                } else {
+                    exercises.join(", ")
+                };
+                writeln!(f, "| {} | {} |", objective.replace('|', "\\|"), exercises)?;
+            }
+            writeln!(f)?;
+        }
+        if !self.uncovering.is_empty() {
+            writeln!(
+                f,
+                "Exercises that cover no objective: {}",
+                self.uncovering.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Render a numbered list of questions and their answers
+fn answer_key_entries(questions: &[&Question]) -> String {
+    let mut entries = String::new();
+    for (question, n) in questions.iter().zip(1..) {
+        entries += &format!(
+            "{n}. {}\n   **Answer:** {}\n",
+            question.question.trim(),
+            question.labelled_answer().trim()
+        );
+        if let Some(explanation) = &question.explanation {
+            entries += &format!("   {}\n", explanation.trim().replace('\n', "\n   "));
+        }
+    }
+    entries
+}
+
+/// Collect the speaker notes from Slidev content. Like Slidev, the last comment
+/// of each slide is taken to be its notes.
+fn speaker_notes(content: &str) -> Vec<SlideNotes<'_>> {
+    let mut slides = vec![vec![]];
+    let mut in_code_block = false;
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        if line.trim_end() == "---" {
+            slides.push(vec![]);
+        } else {
+            slides.last_mut().unwrap().push(line);
+        }
+    }
+
+    slides
+        .into_iter()
+        .filter_map(|lines| {
+            let end = lines.iter().rposition(|l| l.contains("-->"))?;
+            let start = lines[..=end].iter().rposition(|l| l.contains("<!--"))?;
+            let comment = lines[start..=end].join("\n");
+            let notes = comment
+                .split_once("<!--")?
+                .1
+                .rsplit_once("-->")?
+                .0
+                .trim()
+                .to_string();
+            let title = lines
+                .iter()
+                .find(|l| l.starts_with('#'))
+                .map(|l| l.trim_start_matches('#').trim());
+            (!notes.is_empty()).then_some(SlideNotes { title, notes })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{load::Indexed, Exercise, Topic};
+
+    fn topic(objectives: &[&str], exercises: &[(&str, &[&str])]) -> Indexed<Topic> {
+        Indexed {
+            data: Topic {
+                name: "Topic".into(),
+                path: PathBuf::new(),
+                exercises: exercises
+                    .iter()
+                    .zip(1..)
+                    .map(|((name, covers), index)| Indexed {
+                        data: Exercise {
+                            name: name.to_string(),
+                            path: PathBuf::new(),
+                            description: PathBuf::new(),
+                            description_images: vec![],
+                            includes: vec![],
+                            excludes: vec![],
+                            gitignore: false,
+                            hidden_tests: vec![],
+                            covers: covers.iter().map(|c| c.to_string()).collect(),
+                        },
+                        index,
+                    })
+                    .collect(),
+                summary: vec![],
+                objectives: objectives.iter().map(|o| o.to_string()).collect(),
+                content: PathBuf::new(),
+                further_reading: vec![],
+                images: vec![],
+                quiz: None,
+                duration_minutes: None,
+            },
+            index: 1,
+        }
+    }
+
+    #[test]
+    fn unit_coverage_numbers_exercises_within_the_unit() {
+        let unit = Unit {
+            name: "Traits".into(),
+            template: None,
+            topics: vec![
+                topic(
+                    &["Define a trait", "Implement a trait "],
+                    &[("Shapes", &["Implement a trait"]), ("Notes", &[])],
+                ),
+                topic(&["Use generics"], &[("Generic shapes", &[" Use generics"])]),
+            ],
+        };
+        let coverage = unit_coverage("2.3", &unit);
+        assert_eq!(
+            coverage.objectives,
+            [
+                ("Define a trait", vec![]),
+                ("Implement a trait", vec!["2.3.1 Shapes".to_string()]),
+                ("Use generics", vec!["2.3.3 Generic shapes".to_string()]),
+            ]
+        );
+        assert_eq!(coverage.uncovering, ["2.3.2 Notes"]);
+        assert_eq!(
+            coverage.to_string(),
+            r#"| Objective | Exercises |
+|---|---|
+| Define a trait | **None** |
+| Implement a trait | 2.3.1 Shapes |
+| Use generics | 2.3.3 Generic shapes |
+
+Exercises that cover no objective: 2.3.2 Notes
+"#
+        );
+    }
+
+    #[test]
+    fn speaker_notes_takes_last_comment_of_each_slide() {
+        let content = r#"---
+layout: default
+---
+
+# Slices
+
+```rust
+// <!-- not a note -->
+```
+
+<!-- an earlier comment -->
+
+<!--
+- Slices are always behind a pointer
+-->
+
+---
+
+# Strings
+
+No notes here
+"#;
+        assert_eq!(
+            speaker_notes(content),
+            vec![SlideNotes {
+                title: Some("Slices"),
+                notes: "- Slices are always behind a pointer".to_string(),
+            }]
+        );
+    }
+}
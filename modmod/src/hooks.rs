@@ -0,0 +1,255 @@
+use std::{
+    fmt,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct RenderHookError;
+
+impl fmt::Display for RenderHookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("render hook failed")
+    }
+}
+
+impl error_stack::Context for RenderHookError {}
+
+/// The phases of rendering a track, in the order they are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderPhase {
+    Exercises,
+    Book,
+    Slides,
+    InstructorGuide,
+}
+
+impl RenderPhase {
+    /// The name of the phase, as used in the `hooks` table of a track definition
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RenderPhase::Exercises => "exercises",
+            RenderPhase::Book => "book",
+            RenderPhase::Slides => "slides",
+            RenderPhase::InstructorGuide => "instructor_guide",
+        }
+    }
+
+    /// The folder in the output directory the phase writes to
+    pub fn output_subdir(&self) -> &'static str {
+        match self {
+            RenderPhase::Exercises => "exercises",
+            RenderPhase::Book => "book",
+            RenderPhase::Slides => "slides",
+            RenderPhase::InstructorGuide => "instructor-guide",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStage {
+    Before,
+    After,
+}
+
+impl HookStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookStage::Before => "before",
+            HookStage::After => "after",
+        }
+    }
+}
+
+/// Describes a render phase to a hook
+#[derive(Debug, Serialize)]
+pub struct HookContext<'a> {
+    pub phase: RenderPhase,
+    pub stage: HookStage,
+    pub track: &'a str,
+    /// The folder the phase writes to
+    pub output_dir: PathBuf,
+    /// The items rendered in this phase: exercises for the exercises phase,
+    /// and units for the book and slides phases
+    pub items: Vec<RenderedItem<'a>>,
+    /// The files written in this phase. Empty before the phase has run.
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenderedItem<'a> {
+    /// Reference of the item, for instance `2.3` for a unit or `2.3.1` for an exercise
+    pub reference: String,
+    pub name: &'a str,
+    /// The output path of the item, relative to the output directory, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+}
+
+/// Runs custom processing before and after each render phase,
+/// for instance to format rendered exercises or to post-process images
+pub trait RenderHook {
+    fn before(&self, _ctx: &HookContext) -> Result<(), RenderHookError> {
+        Ok(())
+    }
+
+    fn after(&self, _ctx: &HookContext) -> Result<(), RenderHookError> {
+        Ok(())
+    }
+}
+
+/// A shell command that is declared in the `hooks` table of a track definition.
+/// The command gets a JSON description of the phase on its standard input,
+/// and the `MODMOD_PHASE`, `MODMOD_STAGE` and `MODMOD_OUTPUT_DIR` environment variables.
+#[derive(Debug, Clone)]
+pub struct CommandHook {
+    pub phase: RenderPhase,
+    pub stage: HookStage,
+    pub command: String,
+    /// The folder the command is run in, which is the folder of the track definition
+    pub working_dir: PathBuf,
+}
+
+impl CommandHook {
+    fn run(&self, ctx: &HookContext) -> Result<(), RenderHookError> {
+        if ctx.phase != self.phase || ctx.stage != self.stage {
+            return Ok(());
+        }
+
+        let mut command = shell_command(&self.command);
+        command
+            .current_dir(&self.working_dir)
+            .env("MODMOD_PHASE", self.phase.as_str())
+            .env("MODMOD_STAGE", self.stage.as_str())
+            .env("MODMOD_OUTPUT_DIR", &ctx.output_dir)
+            .stdin(Stdio::piped());
+
+        let mut child = command
+            .spawn()
+            .into_report()
+            .attach_printable_lazy(|| format!("Unable to run hook `{}`", self.command))
+            .change_context(RenderHookError)?;
+        // Hooks that don't read their input may close stdin early, so ignore write errors
+        if let Some(mut stdin) = child.stdin.take() {
+            serde_json::to_writer_pretty(&mut stdin, ctx).ok();
+            stdin.flush().ok();
+        }
+        let status = child.wait().into_report().change_context(RenderHookError)?;
+
+        if !status.success() {
+            return Err(Report::new(RenderHookError)
+                .attach_printable(format!("Hook `{}` exited unsuccessfully", self.command))
+                .attach_printable(status));
+        }
+        Ok(())
+    }
+}
+
+impl RenderHook for CommandHook {
+    fn before(&self, ctx: &HookContext) -> Result<(), RenderHookError> {
+        self.run(ctx)
+    }
+
+    fn after(&self, ctx: &HookContext) -> Result<(), RenderHookError> {
+        self.run(ctx)
+    }
+}
+
+/// Run the hooks for a stage of a render phase, in order
+pub(crate) fn run_hooks<'h>(
+    hooks: impl IntoIterator<Item = &'h dyn RenderHook>,
+    ctx: &HookContext,
+) -> Result<(), RenderHookError> {
+    for hook in hooks {
+        match ctx.stage {
+            HookStage::Before => hook.before(ctx)?,
+            HookStage::After => hook.after(ctx)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::io::test_dir;
+
+    #[test]
+    fn phase_names_match_serde_names() {
+        for phase in [
+            RenderPhase::Exercises,
+            RenderPhase::Book,
+            RenderPhase::Slides,
+            RenderPhase::InstructorGuide,
+        ] {
+            let serialized = serde_json::to_value(phase).unwrap();
+            assert_eq!(serialized, phase.as_str());
+        }
+    }
+
+    #[test]
+    fn command_hook_gets_context_on_stdin() {
+        let dir = test_dir("hooks");
+        let hook = CommandHook {
+            phase: RenderPhase::Book,
+            stage: HookStage::After,
+            command: r#"cat > context.json && echo "$MODMOD_PHASE $MODMOD_STAGE" > env.txt"#.into(),
+            working_dir: dir.clone(),
+        };
+        let mut ctx = HookContext {
+            phase: RenderPhase::Book,
+            stage: HookStage::Before,
+            track: "Rust Intro",
+            output_dir: dir.join("book"),
+            items: vec![RenderedItem {
+                reference: "1.2".into(),
+                name: "Traits",
+                output: None,
+            }],
+            files: vec![],
+        };
+        run_hooks([&hook as &dyn RenderHook], &ctx).unwrap();
+        assert!(!dir.join("context.json").exists());
+
+        ctx.stage = HookStage::After;
+        ctx.files = vec![dir.join("book/src/SUMMARY.md")];
+        run_hooks([&hook as &dyn RenderHook], &ctx).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("context.json")).unwrap())
+                .unwrap();
+        assert_eq!(json["phase"], "book");
+        assert_eq!(json["stage"], "after");
+        assert_eq!(json["track"], "Rust Intro");
+        assert_eq!(json["items"][0]["reference"], "1.2");
+        assert_eq!(json["items"][0]["name"], "Traits");
+        assert_eq!(
+            json["files"][0].as_str().unwrap(),
+            dir.join("book/src/SUMMARY.md").to_str().unwrap()
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("env.txt")).unwrap(),
+            "book after\n"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::{fmt, path::Path};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::io::PathExt;
+
+const INCLUDE_PLACEHOLDER_PREFIX: &str = "#[modmod:include ";
+const ANCHOR_START: &str = "ANCHOR:";
+const ANCHOR_END: &str = "ANCHOR_END:";
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct IncludeError;
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to include file")
+    }
+}
+
+impl error_stack::Context for IncludeError {}
+
+/// Replace `#[modmod:include <path>]` placeholders in `content` with the contents of the
+/// file at `<path>`, relative to `base_dir`. A part of the file can be selected by appending
+/// a line range like `#3-10`, `#3-` or `#-10`, or the name of an anchor like `#setup`.
+/// Anchors are delimited by `ANCHOR: <name>` and `ANCHOR_END: <name>` comments.
+pub(crate) fn resolve_includes(content: &str, base_dir: &Path) -> Result<String, IncludeError> {
+    let mut resolved = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find(INCLUDE_PLACEHOLDER_PREFIX) {
+        let after_prefix = &rest[start + INCLUDE_PLACEHOLDER_PREFIX.len()..];
+        let Some(end) = after_prefix.find(']') else {
+            break;
+        };
+        resolved += &rest[..start];
+        let target = after_prefix[..end].trim();
+        let (path, selector) = match target.split_once('#') {
+            Some((path, selector)) => (path, Some(selector)),
+            None => (target, None),
+        };
+        let file = base_dir.join(path);
+        let file_content = file
+            .read_to_string::<IncludeError>()
+            .attach_printable_lazy(|| format!("Unable to include `{target}`"))?;
+        let included = select_lines(&file_content, selector)
+            .attach_printable_lazy(|| format!("Unable to include `{target}`"))?;
+        resolved += included.trim_end_matches('\n');
+        rest = &after_prefix[end + 1..];
+    }
+    resolved += rest;
+    Ok(resolved)
+}
+
+/// Select the lines of `content` given by a line range or anchor name.
+/// Lines with anchor comments are left out, and the selected lines are dedented.
+fn select_lines(content: &str, selector: Option<&str>) -> Result<String, IncludeError> {
+    let lines: Vec<_> = content.lines().collect();
+    let selected = match selector {
+        None => &lines[..],
+        Some(range) if range.contains('-') && !range.contains(char::is_alphabetic) => {
+            let (from, to) = range.split_once('-').unwrap();
+            let parse = |n: &str, default: usize| match n.trim() {
+                "" => Ok(default),
+                n => n.parse::<usize>().map_err(|_| {
+                    Report::new(IncludeError)
+                        .attach_printable(format!("Invalid line range {range}"))
+                }),
+            };
+            let (from, to) = (parse(from, 1)?, parse(to, lines.len())?);
+            if from == 0 || from > to || to > lines.len() {
+                return Err(Report::new(IncludeError).attach_printable(format!(
+                    "Line range {range} is out of bounds for a file of {} lines",
+                    lines.len()
+                )));
+            }
+            &lines[from - 1..to]
+        }
+        Some(anchor) => {
+            let is_marker = |line: &str, marker: &str| {
+                line.split_once(marker)
+                    .is_some_and(|(_, name)| name.trim() == anchor)
+            };
+            let Some(start) = lines.iter().position(|l| is_marker(l, ANCHOR_START)) else {
+                return Err(Report::new(IncludeError)
+                    .attach_printable(format!("Anchor `{anchor}` not found")));
+            };
+            let Some(len) = lines[start..].iter().position(|l| is_marker(l, ANCHOR_END)) else {
+                return Err(Report::new(IncludeError)
+                    .attach_printable(format!("Anchor `{anchor}` is not closed")));
+            };
+            &lines[start + 1..start + len]
+        }
+    };
+
+    let selected: Vec<_> = selected
+        .iter()
+        .filter(|l| !l.contains(ANCHOR_START) && !l.contains(ANCHOR_END))
+        .collect();
+    let indent = selected
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or_default();
+    Ok(selected
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or_default())
+        .fold(String::new(), |acc, l| acc + l + "\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_lines_by_range_and_anchor() {
+        let content = r#"use std::fmt;
+
+fn main() {
+    // ANCHOR: body
+    let x = 1;
+
+    println!("{x}");
+    // ANCHOR_END: body
+}
+"#;
+        assert_eq!(select_lines(content, None).unwrap().lines().count(), 7);
+        assert_eq!(
+            select_lines(content, Some("1-3")).unwrap(),
+            "use std::fmt;\n\nfn main() {\n"
+        );
+        assert_eq!(select_lines(content, Some("9-")).unwrap(), "}\n");
+        assert_eq!(
+            select_lines(content, Some("body")).unwrap(),
+            "let x = 1;\n\nprintln!(\"{x}\");\n"
+        );
+        assert!(select_lines(content, Some("5-20")).is_err());
+        assert!(select_lines(content, Some("setup")).is_err());
+    }
+}
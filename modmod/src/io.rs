@@ -1,11 +1,14 @@
 use std::{
     fmt,
     fs::{self, File},
-    io,
+    io::{self, Read},
     path::Path,
+    process::{Command, Output},
+    sync::mpsc,
+    thread,
 };
 
-use error_stack::{Context, IntoReport, Result, ResultExt};
+use error_stack::{Context, IntoReport, Report, Result, ResultExt};
 use fs_extra::dir::DirContent;
 
 pub trait PathExt {
@@ -143,3 +146,92 @@ pub fn copy_files<P: AsRef<Path>, C: Context + Default>(files: &[P], dest: &Path
         .filter_map(|path| path.as_ref().file_name().map(|name| (path, name)))
         .try_for_each(|(path, name)| path.copy(dest.join(name)))
 }
+
+/// Run `command` to completion, failing if it exits unsuccessfully
+pub fn run_command<C: Context + Default>(command: &mut Command) -> Result<Output, C> {
+    let output = command
+        .output()
+        .into_report()
+        .attach_printable_lazy(|| {
+            format!(
+                "Unable to run {program}. Make sure it is installed.",
+                program = command.get_program().to_string_lossy()
+            )
+        })
+        .change_context(C::default())?;
+
+    if !output.status.success() {
+        return Err(Report::new(C::default())
+            .attach_printable(format!("`{command:?}` exited unsuccessfully"))
+            .attach_printable(format!(
+                r#"Stderr: "{}""#,
+                String::from_utf8_lossy(&output.stderr)
+            ))
+            .attach_printable(output.status));
+    }
+
+    Ok(output)
+}
+
+/// Escape text for use in XML and HTML
+pub(crate) fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&apos;",
+            // Control characters other than whitespace are not allowed in XML
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Read `pipe` to the end on a separate thread, so that the process writing to it
+/// doesn't block on a full pipe. The output is sent over the returned channel.
+pub(crate) fn read_in_background(
+    mut pipe: impl Read + Send + 'static,
+) -> mpsc::Receiver<io::Result<String>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut s = String::new();
+        tx.send(pipe.read_to_string(&mut s).map(|_| s)).ok();
+    });
+    rx
+}
+
+/// An empty scratch directory for a test, named after it
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("modmod-test-{}-{name}", std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write `content` to `path` within a test directory, creating parent directories
+#[cfg(test)]
+pub(crate) fn write_test_file(path: impl AsRef<Path>, content: &str) {
+    let path = path.as_ref();
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_xml_escapes_markup_and_drops_control_characters() {
+        assert_eq!(
+            escape_xml("assert a < b && \"x\"\u{1b}[31m"),
+            "assert a &lt; b &amp;&amp; &quot;x&quot;[31m"
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Result, ResultExt};
+
+use crate::{
+    archive::track_archive_name,
+    io::{escape_xml, WriteExt},
+    RenderReport, RenderedBook, RenderedSlides, Track,
+};
+
+const LANDING_PAGE_TEMPLATE: &str = include_str!("../include/landing/index.html");
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct RenderLandingPageError;
+
+impl fmt::Display for RenderLandingPageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to render landing page")
+    }
+}
+
+impl error_stack::Context for RenderLandingPageError {}
+
+pub struct LandingPageRenderOptions<'r, 'u> {
+    pub book: &'r RenderedBook,
+    pub slides: &'r RenderedSlides,
+    /// Paths of the exercise archives of each module by module index,
+    /// relative to the output directory
+    pub exercise_archives: &'r BTreeMap<usize, Vec<PathBuf>>,
+    /// The base URL the book is deployed at, with the slides in its `slides` path
+    pub url_base: &'u str,
+}
+
+/// A link to a rendered item, both on the web and in the README
+struct Link {
+    title: String,
+    url: String,
+    readme_url: String,
+}
+
+/// Write an `index.html` for the deployed course, and a `README.md` for the output directory,
+/// that list the modules and units of the track with links to their slides and book pages.
+/// The README links to the deployed course if the track has a `url`, and to the locations
+/// the book and slides are built to otherwise.
+pub(crate) fn render_landing_page(
+    track: &Track,
+    LandingPageRenderOptions {
+        book,
+        slides,
+        exercise_archives,
+        url_base,
+    }: LandingPageRenderOptions,
+    out_dir: impl AsRef<Path>,
+) -> Result<RenderReport, RenderLandingPageError> {
+    let mut report = RenderReport::default();
+    let url_base = url_base.trim_matches('/');
+    let url_base = if url_base.is_empty() {
+        String::new()
+    } else {
+        format!("/{url_base}")
+    };
+    let to_string = |p: &Path| p.to_string_lossy().replace('\\', "/");
+    // Takes a path on the deployed site, relative to the URL base,
+    // and the location the item is built to, relative to the output directory
+    let readme_url = |site_path: &str, built_path: String| match &track.metadata.url {
+        Some(url) => format!("{}/{site_path}", url.trim_end_matches('/')),
+        None => built_path,
+    };
+
+    let mut html = String::new();
+    let mut md = String::new();
+
+    let title = escape_xml(&track.name);
+    writeln!(html, "    <h1>{title}</h1>").unwrap();
+    writeln!(md, "# {}\n", track.name).unwrap();
+    if let Some(description) = &track.metadata.description {
+        writeln!(html, "    <p>{}</p>", escape_xml(description)).unwrap();
+        writeln!(md, "{description}\n").unwrap();
+    }
+    if !track.metadata.authors.is_empty() {
+        let authors = track.metadata.authors.join(", ");
+        writeln!(html, "    <p>By {}</p>", escape_xml(&authors)).unwrap();
+        writeln!(md, "By {authors}\n").unwrap();
+    }
+    // The landing page is deployed over the index of the book, so link to its first page instead
+    let first_page = book
+        .unit_pages
+        .values()
+        .next()
+        .map(|page| to_string(&page.with_extension("html")))
+        .unwrap_or_default();
+    let links = [
+        Link {
+            title: "Exercise book".into(),
+            url: format!("{url_base}/{first_page}"),
+            readme_url: readme_url(&first_page, format!("book/target/{first_page}")),
+        },
+        Link {
+            title: "Slides".into(),
+            url: format!("{url_base}/slides/"),
+            readme_url: readme_url("slides/", "slides/dist/index.html".into()),
+        },
+        Link {
+            title: "Search".into(),
+            url: format!("{url_base}/slides/search/"),
+            readme_url: readme_url("slides/search/", "slides/dist/search/index.html".into()),
+        },
+    ];
+    writeln!(html, "    <p>{}</p>", html_links(&links)).unwrap();
+    writeln!(md, "{}\n", md_links(&links)).unwrap();
+    if track.metadata.url.is_none() {
+        md += "The links point to where `mdbook build` in `book` and `npm run build-all` in `slides` put the book and the slides.\n\n";
+    }
+
+    for module in track.modules.iter() {
+        let module_title = format!("Module {}: {}", module.index, module.data.name);
+        writeln!(html, "    <h2>{}</h2>", escape_xml(&module_title)).unwrap();
+        writeln!(md, "## {module_title}\n").unwrap();
+        let description = module.data.description.trim();
+        if !description.is_empty() {
+            writeln!(html, "    <p>{}</p>", escape_xml(description)).unwrap();
+            writeln!(md, "{description}\n").unwrap();
+        }
+
+        html += "    <ul>\n";
+        for unit in module.data.units.iter() {
+            let key = (module.index, unit.index);
+            let slides = slides.deck_paths.get(&key).map(|_| {
+                let deck = format!("{}_{}", module.index, unit.index);
+                Link {
+                    title: "Slides".into(),
+                    url: format!("{url_base}/slides/{deck}/"),
+                    readme_url: readme_url(
+                        &format!("slides/{deck}/"),
+                        format!("slides/dist/{deck}/index.html"),
+                    ),
+                }
+            });
+            let book = book.unit_pages.get(&key).map(|page| {
+                let page = to_string(&page.with_extension("html"));
+                Link {
+                    title: "Book".into(),
+                    url: format!("{url_base}/{page}"),
+                    readme_url: readme_url(&page, format!("book/target/{page}")),
+                }
+            });
+            let links: Vec<_> = [slides, book].into_iter().flatten().collect();
+
+            let unit_title = format!("{}.{} {}", module.index, unit.index, unit.data.name);
+            writeln!(
+                html,
+                "        <li>{} &middot; {}</li>",
+                escape_xml(&unit_title),
+                html_links(&links)
+            )
+            .unwrap();
+            writeln!(md, "- {unit_title}: {}", md_links(&links)).unwrap();
+        }
+        html += "    </ul>\n";
+        md += "\n";
+
+        if let Some(archives) = exercise_archives.get(&module.index) {
+            let links = archive_links(archives, &url_base);
+            writeln!(
+                html,
+                "    <p>Download the exercises of this module: {}</p>",
+                links.0
+            )
+            .unwrap();
+            writeln!(md, "Download the exercises of this module: {}\n", links.1).unwrap();
+        }
+    }
+
+    html += "    <h2>Getting the exercises</h2>\n";
+    md += "## Getting the exercises\n\n";
+    let exercises_note = "Each exercise is a Cargo package. Follow the instructions in the exercise book to complete them.";
+    if exercise_archives.is_empty() {
+        writeln!(html, "    <p>The exercises are in the <code>exercises</code> folder of the course. {exercises_note}</p>").unwrap();
+        writeln!(
+            md,
+            "The exercises are in the [exercises](exercises) folder. {exercises_note}"
+        )
+        .unwrap();
+    } else {
+        let name = track_archive_name(&track.name);
+        let archives = ["tar.gz", "zip"].map(|ext| PathBuf::from(format!("archives/{name}.{ext}")));
+        let links = archive_links(&archives, &url_base);
+        writeln!(
+            html,
+            "    <p>Download all exercises: {}. {exercises_note}</p>",
+            links.0
+        )
+        .unwrap();
+        writeln!(md, "Download all exercises: {}. {exercises_note}", links.1).unwrap();
+    }
+
+    let html = LANDING_PAGE_TEMPLATE
+        .replace("#[modmod:track_title]", &title)
+        .replace("#[modmod:content]\n", &html);
+    // Don't silently replace an index that was put in the output directory some other way
+    report
+        .try_create_file(out_dir.as_ref().join("index.html"), false)
+        .attach_printable("The landing page would overwrite an existing index.html")?
+        .write_all(html)?;
+    report
+        .create_file(out_dir.as_ref().join("README.md"))?
+        .write_all(md)?;
+
+    Ok(report)
+}
+
+fn html_links(links: &[Link]) -> String {
+    links
+        .iter()
+        .map(|l| format!("<a href=\"{}\">{}</a>", l.url, l.title))
+        .collect::<Vec<_>>()
+        .join(" &middot; ")
+}
+
+fn md_links(links: &[Link]) -> String {
+    links
+        .iter()
+        .map(|l| format!("[{}]({})", l.title, l.readme_url))
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// Links to exercise archives in HTML, pointing to the copies in the deployed book,
+/// and in Markdown, pointing into the output directory
+fn archive_links(archives: &[PathBuf], url_base: &str) -> (String, String) {
+    let names: Vec<_> = archives
+        .iter()
+        .map(|a| {
+            (
+                a.file_name().unwrap().to_string_lossy(),
+                a.to_string_lossy(),
+            )
+        })
+        .collect();
+    let html = names
+        .iter()
+        .map(|(name, _)| format!("<a href=\"{url_base}/archives/{name}\">{name}</a>"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let md = names
+        .iter()
+        .map(|(name, path)| format!("[{name}]({path})"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    (html, md)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        io::test_dir,
+        load::{Indexed, TrackMetadata},
+        Module, Unit,
+    };
+
+    fn track(url: Option<&str>) -> Track {
+        Track {
+            name: "Rust <Intro>".into(),
+            metadata: TrackMetadata {
+                url: url.map(String::from),
+                ..Default::default()
+            },
+            modules: vec![Indexed {
+                data: Module {
+                    name: "Basics".into(),
+                    description: String::new(),
+                    units: vec![Indexed {
+                        data: Unit {
+                            name: "Syntax".into(),
+                            template: None,
+                            topics: vec![],
+                        },
+                        index: 2,
+                    }],
+                    variables: BTreeMap::new(),
+                },
+                index: 1,
+            }],
+            edition: None,
+            rust_version: None,
+            hooks: vec![],
+            variables: BTreeMap::new(),
+            book_config: toml::Table::new(),
+            book_files: vec![],
+        }
+    }
+
+    fn render(track: &Track, out_dir: &Path) -> Result<RenderReport, RenderLandingPageError> {
+        let book = RenderedBook {
+            unit_pages: BTreeMap::from([((1, 2), PathBuf::from("1-basics/2-syntax.md"))]),
+            report: RenderReport::default(),
+        };
+        let slides = RenderedSlides {
+            deck_paths: BTreeMap::from([((1, 2), PathBuf::from("1_2-syntax.md"))]),
+            report: RenderReport::default(),
+        };
+        let opts = LandingPageRenderOptions {
+            book: &book,
+            slides: &slides,
+            exercise_archives: &BTreeMap::new(),
+            url_base: "/course/",
+        };
+        render_landing_page(track, opts, out_dir)
+    }
+
+    #[test]
+    fn landing_page_links_to_rendered_book_and_slides() {
+        let out_dir = test_dir("landing");
+        let report = render(&track(None), &out_dir).unwrap();
+        assert_eq!(report.files.len(), 2);
+
+        let html = std::fs::read_to_string(out_dir.join("index.html")).unwrap();
+        assert!(html.contains("<title>Rust &lt;Intro&gt;</title>"));
+        assert!(html.contains(
+            r#"<li>1.2 Syntax &middot; <a href="/course/slides/1_2/">Slides</a> &middot; <a href="/course/1-basics/2-syntax.html">Book</a></li>"#
+        ));
+
+        let readme = std::fs::read_to_string(out_dir.join("README.md")).unwrap();
+        assert!(readme.contains("[Exercise book](book/target/1-basics/2-syntax.html) · [Slides](slides/dist/index.html) · [Search](slides/dist/search/index.html)"));
+        assert!(readme.contains(
+            "- 1.2 Syntax: [Slides](slides/dist/1_2/index.html) · [Book](book/target/1-basics/2-syntax.html)"
+        ));
+    }
+
+    #[test]
+    fn readme_links_to_deployed_course() {
+        let out_dir = test_dir("landing-url");
+        render(&track(Some("https://example.com/course/")), &out_dir).unwrap();
+        let readme = std::fs::read_to_string(out_dir.join("README.md")).unwrap();
+        assert!(readme.contains(
+            "- 1.2 Syntax: [Slides](https://example.com/course/slides/1_2/) · [Book](https://example.com/course/1-basics/2-syntax.html)"
+        ));
+    }
+
+    #[test]
+    fn landing_page_does_not_overwrite_index() {
+        let out_dir = test_dir("landing-index");
+        std::fs::write(out_dir.join("index.html"), "book index").unwrap();
+        assert!(render(&track(None), &out_dir).is_err());
+        assert_eq!(
+            std::fs::read_to_string(out_dir.join("index.html")).unwrap(),
+            "book index"
+        );
+    }
+}
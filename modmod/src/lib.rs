@@ -1,40 +1,113 @@
+//! Stitches teach-rs content together into exercise packages, an exercise book and slides.
+//!
+//! Load a [`Track`] with [`Track::load_toml_def`], inspect or modify it in memory, and
+//! render it with [`Track::render`]. To render only parts of a track, call
+//! [`Track::render_exercises`], [`Track::render_book`] and [`Track::render_slides`] separately.
+//! Each renderer reports the files it wrote in a [`RenderReport`]. Renderers whose output is
+//! needed by later renderers return it along with their report, like [`RenderedExercises`],
+//! [`RenderedBook`] and [`RenderedSlides`].
+//!
+//! ```no_run
+//! use modmod::{slug::SlugRegistry, BookRenderOptions, Track};
+//!
+//! let mut track = Track::load_toml_def("content/rust-intro.track.toml").unwrap();
+//! track.name = "Rust for ACME".into();
+//!
+//! let mut slugs = SlugRegistry::default();
+//! let exercises = track.render_exercises("out", &mut slugs).unwrap();
+//! let book = track
+//!     .render_book(
+//!         BookRenderOptions {
+//!             exercise_paths: &exercises.exercise_paths,
+//!             exercise_archives: &Default::default(),
+//!             slides_url_base: "/",
+//!         },
+//!         "out",
+//!         &mut slugs,
+//!     )
+//!     .unwrap();
+//! println!(
+//!     "Wrote {} files",
+//!     exercises.report.files.len() + book.report.files.len()
+//! );
+//! ```
+
+pub mod archive;
 mod book;
+mod cargo;
 mod exercises;
+pub mod git;
+pub mod grade;
+mod guide;
+pub mod hooks;
+mod include;
 pub mod io;
+mod landing;
 pub mod load;
 pub mod patch;
+pub mod progress;
+mod search;
 mod slides;
+pub mod slug;
+pub mod snippets;
+mod variables;
+pub mod vendor;
 
 use self::{
     book::{Book, BookBuilder, ChapterBuilder, SectionBuilder},
-    load::{Load, TrackDef},
+    load::{Load, Quiz, TrackDef, TrackMetadata},
 };
-use book::BookRenderOptions;
 use error_stack::{IntoReport, Report, Result, ResultExt};
 use exercises::{
-    ExerciseCollection, ExerciseCollectionBuilder, ModuleExercisesBuilder, UnitExercisesBuilder,
+    ExerciseCollection, ExerciseCollectionBuilder, ExerciseRenderOptions, ModuleExercisesBuilder,
+    UnitExercisesBuilder,
 };
+use hooks::{CommandHook, HookContext, HookStage, RenderHook, RenderPhase, RenderedItem};
 use io::PathExt;
 use load::Indexed;
 use slides::{SlideDeckBuilder, SlidesPackage, SlidesPackageBuilder};
+use slug::{SlugCollisionPolicy, SlugRegistry};
 use std::{
-    fmt::{self, Display},
-    fs,
+    collections::BTreeMap,
+    fmt, fs,
     path::{Path, PathBuf},
 };
+use vendor::VendorOptions;
 
-pub use slides::SlidesRenderOptions;
+pub use book::{BookRenderOptions, RenderBookError, RenderedBook};
+pub use cargo::{TestOutcome, TestRunOptions};
+pub use exercises::{RenderExercisesError, RenderedExercises};
+pub use guide::RenderGuideError;
+pub use landing::{LandingPageRenderOptions, RenderLandingPageError};
+pub use search::RenderSearchIndexError;
+pub use slides::{RenderSlidesError, RenderedSlides, SlideImageLayout, SlidesRenderOptions};
 
 pub struct TrackRenderOptions<'t, 'u, O: AsRef<Path>, P: AsRef<Path>> {
     pub out_dir: O,
     pub slide_opts: SlidesRenderOptions<'t, 'u, P>,
     pub clear_output_dir: bool,
+    pub slug_collisions: SlugCollisionPolicy,
+    pub exercise_archives: bool,
+    /// Vendor the dependencies of the exercises, so that they can be built offline
+    pub vendor: Option<VendorOptions>,
+    /// Hooks to run before and after each render phase,
+    /// after the hooks declared in the track definition
+    pub hooks: Vec<Box<dyn RenderHook>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Track {
     pub name: String,
+    pub metadata: TrackMetadata,
     pub modules: Vec<Indexed<Module>>,
+    pub edition: Option<String>,
+    pub rust_version: Option<String>,
+    pub hooks: Vec<CommandHook>,
+    pub variables: BTreeMap<String, String>,
+    /// mdBook configuration that is merged into the generated `book.toml`
+    pub book_config: toml::Table,
+    /// Files and folders to copy into the book folder
+    pub book_files: Vec<PathBuf>,
 }
 
 impl Track {
@@ -43,14 +116,21 @@ impl Track {
         def.resolve().change_context(LoadTrackError)
     }
 
+    /// Render the exercises, the exercise book, the slides, the instructor guide and the landing page
+    /// of the track into `out_dir`, along with the exercise archives and vendored dependencies if requested.
+    /// Returns a report of all files written.
     pub fn render<O: AsRef<Path>, P: AsRef<Path>>(
         &self,
         TrackRenderOptions {
             out_dir,
             slide_opts,
             clear_output_dir,
+            slug_collisions,
+            exercise_archives,
+            vendor,
+            hooks,
         }: TrackRenderOptions<'_, '_, O, P>,
-    ) -> Result<(), LoadTrackError> {
+    ) -> Result<RenderReport, LoadTrackError> {
         let out_dir = out_dir.as_ref();
         out_dir.create_dir_all()?;
         let out_dir = &out_dir
@@ -79,9 +159,238 @@ impl Track {
         // Ensure output dir exists
         out_dir.create_dir_all()?;
 
-        // Render the modules in the track
-        let mut book_builder = Book::builder(&self.name);
-        let mut slides_builder = SlidesPackage::builder(&self.name);
+        // Keep track of the slugs used in all outputs, to prevent items from overwriting each other
+        let mut slugs = SlugRegistry::new(slug_collisions);
+        let mut report = RenderReport::default();
+
+        let hooks: Vec<&dyn RenderHook> = self
+            .hooks
+            .iter()
+            .map(|h| h as &dyn RenderHook)
+            .chain(hooks.iter().map(|h| h.as_ref()))
+            .collect();
+        let run_hooks = |phase, stage, exercises: Option<&RenderedExercises>, files: &[PathBuf]| {
+            let ctx = HookContext {
+                phase,
+                stage,
+                track: &self.name,
+                output_dir: out_dir.join(phase.output_subdir()),
+                items: self.hook_items(phase, exercises),
+                files: files.to_vec(),
+            };
+            hooks::run_hooks(hooks.iter().copied(), &ctx).change_context(LoadTrackError)
+        };
+
+        // Render exercise packages
+        run_hooks(RenderPhase::Exercises, HookStage::Before, None, &[])?;
+        let rendered_exercises = self
+            .render_exercises(out_dir, &mut slugs)
+            .change_context(LoadTrackError)?;
+        run_hooks(
+            RenderPhase::Exercises,
+            HookStage::After,
+            Some(&rendered_exercises),
+            &rendered_exercises.report.files,
+        )?;
+        report.extend(rendered_exercises.report.clone());
+        if let Some(vendor_opts) = vendor {
+            report.extend(
+                vendor::vendor_dependencies(&rendered_exercises, out_dir, vendor_opts)
+                    .change_context(LoadTrackError)?,
+            );
+        }
+        // Package the exercises into archives, if requested
+        let exercise_archives = if exercise_archives {
+            let archives = archive::archive_exercises(&rendered_exercises, &self.name, out_dir)
+                .change_context(LoadTrackError)?;
+            report.extend(archives.report);
+            archives.module_archives
+        } else {
+            BTreeMap::new()
+        };
+
+        // Render the exercise book
+        run_hooks(RenderPhase::Book, HookStage::Before, None, &[])?;
+        let book_opts = BookRenderOptions {
+            exercise_paths: &rendered_exercises.exercise_paths,
+            exercise_archives: &exercise_archives,
+            slides_url_base: slide_opts.url_base,
+        };
+        let rendered_book = self
+            .render_book(book_opts, out_dir, &mut slugs)
+            .change_context(LoadTrackError)?;
+        run_hooks(
+            RenderPhase::Book,
+            HookStage::After,
+            None,
+            &rendered_book.report.files,
+        )?;
+        report.extend(rendered_book.report.clone());
+
+        // Render the slides package, along with the search index over the slides and the book
+        run_hooks(RenderPhase::Slides, HookStage::Before, None, &[])?;
+        let url_base = slide_opts.url_base;
+        let rendered_slides = self
+            .render_slides(slide_opts, out_dir, &mut slugs)
+            .change_context(LoadTrackError)?;
+        let mut slides_report = rendered_slides.report.clone();
+        slides_report.extend(
+            self.render_search_index(&rendered_book, url_base, out_dir)
+                .change_context(LoadTrackError)?,
+        );
+        run_hooks(
+            RenderPhase::Slides,
+            HookStage::After,
+            None,
+            &slides_report.files,
+        )?;
+        report.extend(slides_report);
+
+        // Render the instructor guide
+        run_hooks(RenderPhase::InstructorGuide, HookStage::Before, None, &[])?;
+        let guide_report = self
+            .render_instructor_guide(out_dir, &mut slugs)
+            .change_context(LoadTrackError)?;
+        run_hooks(
+            RenderPhase::InstructorGuide,
+            HookStage::After,
+            None,
+            &guide_report.files,
+        )?;
+        report.extend(guide_report);
+
+        // Render the landing page that links everything together
+        let landing_opts = LandingPageRenderOptions {
+            book: &rendered_book,
+            slides: &rendered_slides,
+            exercise_archives: &exercise_archives,
+            url_base,
+        };
+        report.extend(
+            self.render_landing_page(landing_opts, out_dir)
+                .change_context(LoadTrackError)?,
+        );
+
+        Ok(report)
+    }
+
+    /// Render the exercise packages of the track into `out_dir/exercises`
+    pub fn render_exercises(
+        &self,
+        out_dir: impl AsRef<Path>,
+        slugs: &mut SlugRegistry,
+    ) -> Result<RenderedExercises, RenderExercisesError> {
+        let (_, _, exercises) = self
+            .build()
+            .change_context(RenderExercisesError::default())?;
+        let exercise_opts = ExerciseRenderOptions {
+            edition: self.edition.as_deref(),
+            rust_version: self.rust_version.as_deref(),
+        };
+        exercises.render(exercise_opts, out_dir, slugs)
+    }
+
+    /// Render the exercise book of the track into `out_dir/book`.
+    /// Expects the exercises to be rendered into `out_dir` already.
+    pub fn render_book(
+        &self,
+        opts: BookRenderOptions,
+        out_dir: impl AsRef<Path>,
+        slugs: &mut SlugRegistry,
+    ) -> Result<RenderedBook, RenderBookError> {
+        let (book, _, _) = self.build().change_context(RenderBookError::default())?;
+        book.render(opts, out_dir, slugs)
+    }
+
+    /// Render the slides package of the track into `out_dir/slides`
+    pub fn render_slides<P: AsRef<Path>>(
+        &self,
+        opts: SlidesRenderOptions<'_, '_, P>,
+        out_dir: impl AsRef<Path>,
+        slugs: &mut SlugRegistry,
+    ) -> Result<RenderedSlides, RenderSlidesError> {
+        let (_, slides_package, _) = self.build().change_context(RenderSlidesError::default())?;
+        slides_package.render(out_dir, opts, slugs)
+    }
+
+    /// Render a search index over the slides and the exercise descriptions, and a page
+    /// to search it, into `out_dir/slides/search`. Expects the book to be rendered already.
+    /// If the slides are rendered already, their `build-all` script copies the search page too.
+    pub fn render_search_index(
+        &self,
+        book: &RenderedBook,
+        url_base: &str,
+        out_dir: impl AsRef<Path>,
+    ) -> Result<RenderReport, RenderSearchIndexError> {
+        search::render_search_index(self, book, url_base, out_dir)
+    }
+
+    /// Render an `index.html` and a `README.md` into `out_dir` that link to the slides,
+    /// the book pages and the exercises of every unit.
+    /// Expects the book and the slides to be rendered already.
+    pub fn render_landing_page(
+        &self,
+        opts: LandingPageRenderOptions,
+        out_dir: impl AsRef<Path>,
+    ) -> Result<RenderReport, RenderLandingPageError> {
+        landing::render_landing_page(self, opts, out_dir)
+    }
+
+    /// Render a guide for instructors into `out_dir/instructor-guide`, with the speaker notes,
+    /// objectives, estimated durations and exercises of every topic, and the quiz answers
+    pub fn render_instructor_guide(
+        &self,
+        out_dir: impl AsRef<Path>,
+        slugs: &mut SlugRegistry,
+    ) -> Result<RenderReport, RenderGuideError> {
+        guide::render_instructor_guide(self, out_dir, slugs)
+    }
+
+    /// Describe the items rendered in a phase to hooks
+    fn hook_items(
+        &self,
+        phase: RenderPhase,
+        exercises: Option<&RenderedExercises>,
+    ) -> Vec<RenderedItem<'_>> {
+        let units = self.modules.iter().flat_map(|module| {
+            module
+                .data
+                .units
+                .iter()
+                .map(move |unit| (module.index, unit))
+        });
+        match phase {
+            RenderPhase::Exercises => units
+                .flat_map(|(module_index, unit)| {
+                    unit.data
+                        .numbered_exercises()
+                        .map(move |(exercise, exercise_index)| RenderedItem {
+                            reference: format!("{module_index}.{}.{exercise_index}", unit.index),
+                            name: &exercise.data.name,
+                            output: exercises
+                                .and_then(|e| e.exercise_paths.get(&exercise.data.path))
+                                .cloned(),
+                        })
+                })
+                .collect(),
+            RenderPhase::Book | RenderPhase::Slides | RenderPhase::InstructorGuide => units
+                .map(|(module_index, unit)| RenderedItem {
+                    reference: format!("{module_index}.{}", unit.index),
+                    name: &unit.data.name,
+                    output: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Collect the modules in the track into a book, a slides package and an exercise collection
+    fn build(
+        &self,
+    ) -> Result<(Book<'_>, SlidesPackage<'_>, ExerciseCollection<'_>), LoadTrackError> {
+        let mut book_builder = Book::builder(&self.name, &self.metadata, &self.variables)
+            .config(&self.book_config, &self.book_files);
+        let mut slides_builder =
+            SlidesPackage::builder(&self.name, &self.metadata, &self.variables);
         let mut exercises_builder = ExerciseCollection::builder();
 
         self.modules.iter().try_for_each(|module| {
@@ -92,33 +401,72 @@ impl Track {
             )
         })?;
 
-        // Build and render exercise packages
-        let exercises = exercises_builder.build();
-        let exercise_paths = exercises.render(out_dir).change_context(LoadTrackError)?;
-        // Build and render the exercise book
-        let book = book_builder.build();
-        let book_opts = BookRenderOptions {
-            exercise_paths: &exercise_paths,
-            slides_url_base: slide_opts.url_base,
-        };
-        book.render(book_opts, out_dir)
-            .change_context(LoadTrackError)?;
+        Ok((
+            book_builder.build(),
+            slides_builder.build(),
+            exercises_builder.build(),
+        ))
+    }
+}
 
-        // Build and render the slides package
-        let slides_package = slides_builder.build();
-        slides_package
-            .render(out_dir, slide_opts)
-            .change_context(LoadTrackError)?;
+/// Lists the files written by a renderer
+#[derive(Debug, Default, Clone)]
+pub struct RenderReport {
+    /// The paths of the written files, in the order they were written
+    pub files: Vec<PathBuf>,
+}
 
+impl RenderReport {
+    /// Append the files written according to another report
+    pub fn extend(&mut self, other: RenderReport) {
+        self.files.extend(other.files);
+    }
+
+    pub(crate) fn create_file<C: error_stack::Context + Default>(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<fs::File, C> {
+        self.try_create_file(path, true)
+    }
+
+    pub(crate) fn try_create_file<C: error_stack::Context + Default>(
+        &mut self,
+        path: impl AsRef<Path>,
+        force: bool,
+    ) -> Result<fs::File, C> {
+        let file = path.try_create_file(force)?;
+        self.files.push(path.as_ref().to_path_buf());
+        Ok(file)
+    }
+
+    pub(crate) fn copy<C: error_stack::Context + Default>(
+        &mut self,
+        from: impl AsRef<Path>,
+        to: impl AsRef<Path>,
+    ) -> Result<(), C> {
+        from.copy(&to)?;
+        self.files.push(to.as_ref().to_path_buf());
         Ok(())
     }
+
+    pub(crate) fn copy_files<P: AsRef<Path>, C: error_stack::Context + Default>(
+        &mut self,
+        files: &[P],
+        dest: &Path,
+    ) -> Result<(), C> {
+        files
+            .iter()
+            .filter_map(|path| path.as_ref().file_name().map(|name| (path, name)))
+            .try_for_each(|(path, name)| self.copy(path, dest.join(name)))
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Module {
     pub name: String,
     pub description: String,
     pub units: Vec<Indexed<Unit>>,
+    pub variables: BTreeMap<String, String>,
 }
 
 impl Indexed<Module> {
@@ -133,7 +481,7 @@ impl Indexed<Module> {
             index: module_index,
         } = self;
 
-        let mut chapter = book_builder.chapter(&data.name, *module_index);
+        let mut chapter = book_builder.chapter(&data.name, &data.variables);
         let mut module_exercises = exercises.module(&data.name, *module_index);
 
         // Render all units in this module
@@ -141,6 +489,7 @@ impl Indexed<Module> {
             unit.render(
                 &data.name,
                 *module_index,
+                &data.variables,
                 &mut chapter,
                 slides,
                 &mut module_exercises,
@@ -153,18 +502,38 @@ impl Indexed<Module> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Unit {
     pub name: String,
     pub template: Option<PathBuf>,
     pub topics: Vec<Indexed<Topic>>,
 }
 
+impl Unit {
+    /// The exercises of all topics in the unit, along with their number within the unit
+    pub fn numbered_exercises(&self) -> impl Iterator<Item = (&Indexed<Exercise>, usize)> {
+        number_exercises(
+            self.topics
+                .iter()
+                .map(|topic| topic.data.exercises.as_slice()),
+        )
+    }
+}
+
+/// Number the exercises of the topics of a unit, in order. Exercises are numbered
+/// within their unit rather than their topic, starting at 1.
+pub fn number_exercises<'e, E: 'e>(
+    topic_exercises: impl IntoIterator<Item = &'e [E]>,
+) -> impl Iterator<Item = (&'e E, usize)> {
+    topic_exercises.into_iter().flatten().zip(1..)
+}
+
 impl Indexed<Unit> {
     fn render<'me>(
         &'me self,
         module_name: &'me str,
         module_index: usize,
+        module_variables: &'me BTreeMap<String, String>,
         chapter: &mut ChapterBuilder<'me, '_>,
         slides: &mut SlidesPackageBuilder<'me>,
         module_exercises: &mut ModuleExercisesBuilder<'me, '_>,
@@ -174,13 +543,14 @@ impl Indexed<Unit> {
             index: unit_index,
         } = self;
 
-        let mut section = chapter.section(module_index, *unit_index, &data.name);
+        let mut section = chapter.section(&data.name);
         let mut deck = slides.deck(
             &data.name,
             module_name,
             module_index,
             *unit_index,
             data.template.as_deref(),
+            module_variables,
         );
         let mut unit_exercises = module_exercises.unit(&data.name, *unit_index);
 
@@ -196,15 +566,18 @@ impl Indexed<Unit> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Topic {
     pub name: String,
+    pub path: PathBuf,
     pub exercises: Vec<Indexed<Exercise>>,
     pub summary: Vec<String>,
     pub objectives: Vec<String>,
     pub content: PathBuf,
     pub further_reading: Vec<String>,
     pub images: Vec<PathBuf>,
+    pub quiz: Option<Quiz>,
+    pub duration_minutes: Option<u32>,
 }
 
 impl Indexed<Topic> {
@@ -214,9 +587,12 @@ impl Indexed<Topic> {
         deck: &mut SlideDeckBuilder<'me, '_>,
         unit_exercises: &mut UnitExercisesBuilder<'me, '_, '_>,
     ) -> Result<(), LoadTrackError> {
-        let Indexed { data, .. } = self;
+        let Indexed {
+            data,
+            index: topic_index,
+        } = self;
 
-        let mut slides_section = deck.section(&data.content);
+        let mut slides_section = deck.section(*topic_index, &data.path, &data.content);
 
         data.summary
             .iter()
@@ -234,6 +610,11 @@ impl Indexed<Topic> {
             .iter()
             .for_each(|image| slides_section.image(image));
 
+        if let Some(quiz) = &data.quiz {
+            slides_section.quiz(quiz);
+            section.quiz(quiz);
+        }
+
         data.exercises
             .iter()
             .try_for_each(|exercise| exercise.render(section, unit_exercises))?;
@@ -244,13 +625,17 @@ impl Indexed<Topic> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Exercise {
     pub name: String,
     pub path: PathBuf,
     pub description: PathBuf,
     pub description_images: Vec<PathBuf>,
     pub includes: Vec<String>,
+    pub excludes: Vec<String>,
+    pub gitignore: bool,
+    pub hidden_tests: Vec<PathBuf>,
+    pub covers: Vec<String>,
 }
 
 impl Indexed<Exercise> {
@@ -268,7 +653,14 @@ impl Indexed<Exercise> {
             &data.path,
         );
 
-        unit_exercises.package(&data.name, &data.path, &data.includes);
+        unit_exercises.package(
+            &data.name,
+            &data.path,
+            &data.includes,
+            &data.excludes,
+            data.gitignore,
+            &data.hidden_tests,
+        );
 
         Ok(())
     }
@@ -284,32 +676,3 @@ impl fmt::Display for LoadTrackError {
 }
 
 impl error_stack::Context for LoadTrackError {}
-
-fn to_prefixed_tag<S, P>(s: S, p: P) -> String
-where
-    S: Display,
-    P: Display,
-{
-    to_tag(format!("{p}-{s}"))
-}
-
-fn to_tag<S>(s: S) -> String
-where
-    S: ToString,
-{
-    let mut s = s.to_string();
-    s.make_ascii_lowercase();
-    let mut tag = String::new();
-    let mut words = s.split_whitespace();
-
-    match words.next() {
-        Some(w) => tag.push_str(w),
-        None => return s,
-    }
-
-    for word in words {
-        tag.push('-');
-        tag.push_str(word);
-    }
-    tag
-}
@@ -1,5 +1,6 @@
 use std::{
     any::type_name,
+    collections::BTreeMap,
     fmt, fs,
     path::{Path, PathBuf},
 };
@@ -7,15 +8,65 @@ use std::{
 use error_stack::{IntoReport, Result, ResultExt};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::io::PathExt;
+use crate::{
+    hooks::{CommandHook, HookStage, RenderPhase},
+    io::PathExt,
+};
 
 use super::{Exercise, Module, Topic, Track, Unit};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TrackDef {
     pub name: String,
+    #[serde(flatten)]
+    pub metadata: TrackMetadata,
     #[serde(default)]
     pub modules: Vec<PathBuf>,
+    /// Rust edition to set in the `Cargo.toml` of every exercise package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edition: Option<String>,
+    /// Minimum supported Rust version to set in the `Cargo.toml` of every exercise package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rust_version: Option<String>,
+    /// Commands to run before and after each render phase
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hooks: BTreeMap<RenderPhase, PhaseHooksDef>,
+    /// Values for `#[modmod:var.<name>]` placeholders
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    /// mdBook configuration that is merged into the generated `book.toml`
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub book: toml::Table,
+    /// Files and folders to copy into the book folder, such as theme overrides
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub book_files: Vec<PathBuf>,
+}
+
+/// Describes a track and its authors, for use in the generated book and slides
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TrackMetadata {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// URL of the repository containing the course content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// URL the book is deployed at, with the slides in its `slides` path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PhaseHooksDef {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
 }
 
 impl PathTo<TrackDef> {
@@ -26,11 +77,36 @@ impl PathTo<TrackDef> {
         } = self;
         let TrackDef {
             name,
+            metadata,
             modules: module_paths,
+            edition,
+            rust_version,
+            hooks: hook_defs,
+            variables,
+            book: book_config,
+            book_files,
         } = data;
 
         let mut modules = Vec::with_capacity(module_paths.len());
         let base_path = track_path.parent().unwrap();
+
+        // Hook commands are run in the folder of the track definition
+        let working_dir = base_path
+            .canonicalize()
+            .into_report()
+            .change_context(HydrateTrackError)?;
+        let mut hooks = vec![];
+        for (phase, PhaseHooksDef { before, after }) in hook_defs {
+            let before = before.into_iter().map(|c| (HookStage::Before, c));
+            let after = after.into_iter().map(|c| (HookStage::After, c));
+            hooks.extend(before.chain(after).map(|(stage, command)| CommandHook {
+                phase,
+                stage,
+                command,
+                working_dir: working_dir.clone(),
+            }));
+        }
+
         for (module_path, module_index) in module_paths.into_iter().zip(1..) {
             modules.push(
                 ModuleDef::load(&module_path, Some(base_path))
@@ -39,7 +115,31 @@ impl PathTo<TrackDef> {
             );
         }
 
-        Ok(Track { name, modules })
+        let book_files = book_files
+            .into_iter()
+            .map(|file| {
+                base_path
+                    .join(&file)
+                    .canonicalize()
+                    .into_report()
+                    .attach_printable_lazy(|| {
+                        format!("Book file {} does not exist", file.to_string_lossy())
+                    })
+                    .change_context(HydrateTrackError)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Track {
+            name,
+            metadata,
+            modules,
+            edition,
+            rust_version,
+            hooks,
+            variables,
+            book_config,
+            book_files,
+        })
     }
 }
 
@@ -49,10 +149,13 @@ pub struct ModuleDef {
     pub description: String,
     #[serde(default)]
     pub units: Vec<UnitDef>,
+    /// Values for `#[modmod:var.<name>]` placeholders, overriding those of the track
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
 }
 
 impl PathTo<ModuleDef> {
-    fn resolve(self, module_index: usize) -> Result<Indexed<Module>, HydrateTrackError> {
+    pub fn resolve(self, module_index: usize) -> Result<Indexed<Module>, HydrateTrackError> {
         let PathTo {
             data: def,
             path: module_path,
@@ -61,6 +164,7 @@ impl PathTo<ModuleDef> {
             name,
             description,
             units: unit_defs,
+            variables,
         } = def;
 
         let mut units = Vec::with_capacity(unit_defs.len());
@@ -73,6 +177,7 @@ impl PathTo<ModuleDef> {
             name,
             description,
             units,
+            variables,
         }
         .with_index(module_index))
     }
@@ -143,6 +248,9 @@ pub struct TopicDef {
     pub content: PathBuf,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub further_reading: Vec<String>,
+    /// Estimated time it takes to teach the topic, including its exercises
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u32>,
 }
 
 impl Default for TopicDef {
@@ -154,12 +262,13 @@ impl Default for TopicDef {
             objectives: Default::default(),
             content: serde_defaults::topic_slides_md(),
             further_reading: Default::default(),
+            duration_minutes: Default::default(),
         }
     }
 }
 
 impl PathTo<TopicDef> {
-    fn resolve(self, topic_index: usize) -> Result<Indexed<Topic>, HydrateTrackError> {
+    pub fn resolve(self, topic_index: usize) -> Result<Indexed<Topic>, HydrateTrackError> {
         let PathTo {
             data: def,
             path: topic_path,
@@ -172,6 +281,7 @@ impl PathTo<TopicDef> {
             objectives,
             content,
             further_reading,
+            duration_minutes,
         } = def;
 
         let mut exercises = Vec::new();
@@ -186,16 +296,48 @@ impl PathTo<TopicDef> {
             .into_report()
             .change_context(HydrateTrackError)?;
 
+        for exercise in exercises.iter() {
+            let objective = exercise
+                .data
+                .covers
+                .iter()
+                .find(|o| !objectives.iter().any(|obj| obj.trim() == o.trim()));
+            if let Some(objective) = objective {
+                return Err(
+                    error_stack::Report::new(HydrateTrackError).attach_printable(format!(
+                        "Exercise '{}' covers '{objective}', which is not an objective of topic '{name}'",
+                        exercise.data.name
+                    )),
+                );
+            }
+        }
+
         let images = dir_content(&base_path.join("images"))?;
 
+        let quiz_path = base_path.join(serde_defaults::topic_quiz_toml());
+        let quiz = if quiz_path.is_file() {
+            let quiz = Quiz::load(&quiz_path, None)
+                .change_context(HydrateTrackError)?
+                .data;
+            quiz.validate().attach_printable_lazy(|| {
+                format!("Invalid quiz at {}", quiz_path.to_string_lossy())
+            })?;
+            Some(quiz)
+        } else {
+            None
+        };
+
         Ok(Topic {
             name,
+            path: base_path.to_path_buf(),
             exercises,
             summary,
             objectives,
             content,
             further_reading,
             images,
+            quiz,
+            duration_minutes,
         }
         .with_index(topic_index))
     }
@@ -214,6 +356,65 @@ pub fn dir_content(path: &Path) -> Result<Vec<PathBuf>, HydrateTrackError> {
         .collect())
 }
 
+/// Questions to check the understanding of a topic, loaded from the `quiz.toml` next to its `topic.toml`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Quiz {
+    #[serde(default)]
+    pub questions: Vec<Question>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Question {
+    pub question: String,
+    /// The options of a multiple-choice question. Empty for a short-answer question.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+    /// The correct answer. For a multiple-choice question, this must be one of the `choices`.
+    pub answer: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+}
+
+impl Question {
+    /// The choices of a multiple-choice question, labelled `A`, `B`, `C`, ...
+    pub fn labelled_choices(&self) -> impl Iterator<Item = (char, &str)> {
+        ('A'..='Z').zip(self.choices.iter().map(String::as_str))
+    }
+
+    /// The answer, prefixed with its label for multiple-choice questions
+    pub fn labelled_answer(&self) -> String {
+        match self.labelled_choices().find(|(_, c)| *c == self.answer) {
+            Some((label, choice)) => format!("{label}. {choice}"),
+            None => self.answer.clone(),
+        }
+    }
+}
+
+impl Quiz {
+    fn validate(&self) -> Result<(), HydrateTrackError> {
+        for question in self.questions.iter() {
+            // Choices are labelled `A` to `Z`
+            if question.choices.len() > 26 {
+                return Err(
+                    error_stack::Report::new(HydrateTrackError).attach_printable(format!(
+                        "Question '{}' has {} choices, but at most 26 are supported",
+                        question.question,
+                        question.choices.len()
+                    )),
+                );
+            }
+            if !question.choices.is_empty() && !question.choices.contains(&question.answer) {
+                return Err(
+                    error_stack::Report::new(HydrateTrackError).attach_printable(format!(
+                        "The answer to question '{}' is not one of its choices",
+                        question.question
+                    )),
+                );
+            }
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExerciseDef {
@@ -229,6 +430,22 @@ pub struct ExerciseDef {
         skip_serializing_if = "crate::load::serde_defaults::is_exercise_includes"
     )]
     pub includes: Vec<String>,
+    #[serde(
+        default = "crate::load::serde_defaults::exercise_excludes",
+        skip_serializing_if = "crate::load::serde_defaults::is_exercise_excludes"
+    )]
+    pub excludes: Vec<String>,
+    /// Skip files that are ignored by `.gitignore` files inside the exercise crate.
+    /// Files ignored by a `.modmodignore` file are always skipped.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub gitignore: bool,
+    /// Test files, relative to the exercise crate, that are never copied into the
+    /// exercise package, and are only used for grading
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden_tests: Vec<PathBuf>,
+    /// The objectives of the topic that the exercise assesses
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub covers: Vec<String>,
 }
 
 impl Default for ExerciseDef {
@@ -238,6 +455,10 @@ impl Default for ExerciseDef {
             path: Default::default(),
             description: serde_defaults::exercise_description_md(),
             includes: serde_defaults::exercise_includes(),
+            excludes: serde_defaults::exercise_excludes(),
+            gitignore: false,
+            hidden_tests: vec![],
+            covers: vec![],
         }
     }
 }
@@ -253,6 +474,10 @@ impl ExerciseDef {
             path: exercise_path,
             description,
             includes,
+            excludes,
+            gitignore,
+            hidden_tests,
+            covers,
         } = self;
         let path = base_path
             .join(exercise_path)
@@ -265,12 +490,25 @@ impl ExerciseDef {
             .into_report()
             .change_context(HydrateTrackError)?;
         let description_images = dir_content(&path.join("images"))?;
+        if let Some(missing) = hidden_tests.iter().find(|t| !path.join(t).is_file()) {
+            return Err(
+                error_stack::Report::new(HydrateTrackError).attach_printable(format!(
+                    "Hidden test file {} does not exist in exercise {}",
+                    missing.to_string_lossy(),
+                    path.to_string_lossy()
+                )),
+            );
+        }
         Ok(Exercise {
             name,
             path,
             description,
             description_images,
             includes,
+            excludes,
+            gitignore,
+            hidden_tests,
+            covers,
         }
         .with_index(exercise_index))
     }
@@ -327,6 +565,7 @@ pub trait Load: DeserializeOwned + Sized + 'static {
 impl Load for TrackDef {}
 impl Load for ModuleDef {}
 impl Load for TopicDef {}
+impl Load for Quiz {}
 
 #[derive(Debug)]
 pub struct PathTo<T> {
@@ -342,7 +581,7 @@ trait WithPath: Sized {
 
 impl<T> WithPath for T {}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Indexed<T> {
     pub data: T,
     pub index: usize,
@@ -388,6 +627,16 @@ pub mod serde_defaults {
         includes == &exercise_includes()
     }
 
+    pub fn exercise_excludes() -> Vec<String> {
+        ["target/**/*", "**/*~", "**/*.swp", "**/*.bak", "**/*.orig"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    pub fn is_exercise_excludes(excludes: &Vec<String>) -> bool {
+        excludes == &exercise_excludes()
+    }
+
     pub fn topic_slides_md() -> PathBuf {
         PathBuf::from("slides.md")
     }
@@ -395,4 +644,81 @@ pub mod serde_defaults {
     pub fn is_topic_slides_md(path: &PathBuf) -> bool {
         path == &topic_slides_md()
     }
+
+    pub fn topic_quiz_toml() -> PathBuf {
+        PathBuf::from("quiz.toml")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{test_dir, write_test_file};
+
+    #[test]
+    fn topic_exercises_must_cover_topic_objectives() {
+        let dir = test_dir("covers");
+        write_test_file(dir.join("slides.md"), "# Traits\n");
+        write_test_file(dir.join("shapes/description.md"), "# Shapes\n");
+        let topic = |covers: &str| {
+            let def: TopicDef = toml::from_str(&format!(
+                r#"
+name = "Traits"
+objectives = ["Define a trait", "Implement a trait"]
+
+[[exercises]]
+name = "Shapes"
+path = "shapes"
+covers = [{covers}]
+"#
+            ))
+            .unwrap();
+            PathTo {
+                data: def,
+                path: dir.join("topic.toml"),
+            }
+            .resolve(1)
+        };
+
+        let resolved = topic(r#"" Implement a trait ""#).unwrap();
+        assert_eq!(
+            resolved.data.exercises[0].data.covers,
+            [" Implement a trait "]
+        );
+        assert!(topic("").is_ok());
+        assert!(topic(r#""Define a trait", "Use a trait""#).is_err());
+    }
+
+    fn question(choices: &[&str], answer: &str) -> Question {
+        Question {
+            question: "Which one?".into(),
+            choices: choices.iter().map(|c| c.to_string()).collect(),
+            answer: answer.into(),
+            explanation: None,
+        }
+    }
+
+    #[test]
+    fn quiz_validate_checks_answers_and_choice_count() {
+        let quiz = |questions| Quiz { questions };
+        assert!(
+            quiz(vec![question(&["a", "b"], "b"), question(&[], "free")])
+                .validate()
+                .is_ok()
+        );
+        assert!(quiz(vec![question(&["a", "b"], "c")]).validate().is_err());
+
+        let choices: Vec<String> = (0..27).map(|i| i.to_string()).collect();
+        let choices: Vec<&str> = choices.iter().map(String::as_str).collect();
+        assert!(quiz(vec![question(&choices[..26], "25")])
+            .validate()
+            .is_ok());
+        assert!(quiz(vec![question(&choices, "0")]).validate().is_err());
+    }
+
+    #[test]
+    fn labelled_answer_uses_choice_label() {
+        assert_eq!(question(&["a", "b"], "b").labelled_answer(), "B. b");
+        assert_eq!(question(&[], "free").labelled_answer(), "free");
+    }
 }
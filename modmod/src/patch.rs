@@ -8,7 +8,7 @@ use std::{
     path::Path,
 };
 
-use crate::io::PathExt;
+use crate::{io::PathExt, RenderReport};
 
 #[non_exhaustive]
 #[derive(Debug, Default)]
@@ -37,9 +37,10 @@ impl Patch {
             old_dir,
             patch_file,
         }: GenPatchOptions<N, O, P>,
-    ) -> Result<(), GenPatchError> {
+    ) -> Result<RenderReport, GenPatchError> {
         use std::io::Write;
-        let mut patch_file = std::fs::File::create(&patch_file).unwrap();
+        let mut report = RenderReport::default();
+        let mut patch_file = report.create_file(patch_file)?;
 
         'files: for new_file_path in new_dir.as_ref().get_dir_content()?.files {
             let relative_file_path = Path::new(&new_file_path)
@@ -134,6 +135,6 @@ impl Patch {
 
             unreachable!("All types of files should have been handled");
         }
-        Ok(())
+        Ok(report)
     }
 }
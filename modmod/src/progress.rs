@@ -0,0 +1,295 @@
+use std::{
+    fmt::{self, Write},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::Serialize;
+
+use crate::{
+    cargo::{count_todos, run_tests, TestOutcome, TestRunOptions},
+    Track,
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ProgressReportError;
+
+impl fmt::Display for ProgressReportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to create progress report")
+    }
+}
+
+impl error_stack::Context for ProgressReportError {}
+
+pub struct ProgressReportOptions<'a, S: AsRef<Path>> {
+    /// The student's exercises folder
+    pub student_dir: S,
+    /// Run the tests of each exercise package
+    pub run_tests: bool,
+    pub test_opts: TestRunOptions,
+    /// Called with the reference of each exercise before its tests are run,
+    /// for instance to show progress
+    pub on_test_run: Option<&'a dyn Fn(&str)>,
+}
+
+/// The progress of a student on the exercises of a track
+#[derive(Debug, Serialize)]
+pub struct ProgressReport<'track> {
+    pub track: &'track str,
+    pub modules: Vec<ModuleProgress<'track>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleProgress<'track> {
+    pub index: usize,
+    pub name: &'track str,
+    pub units: Vec<UnitProgress<'track>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnitProgress<'track> {
+    pub index: usize,
+    pub name: &'track str,
+    pub exercises: Vec<ExerciseProgress<'track>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExerciseProgress<'track> {
+    /// Reference of the exercise, as used in the exercise book. For instance, `2.3.1`
+    pub reference: String,
+    pub name: &'track str,
+    /// The path of the exercise package in the student's exercises folder
+    pub path: Option<PathBuf>,
+    pub status: ExerciseStatus,
+    pub tests_passed: usize,
+    pub tests_failed: usize,
+    /// The number of `todo!()` invocations left in the exercise package
+    pub todos: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExerciseStatus {
+    /// The exercise package was not found in the student's exercises folder
+    Missing,
+    /// Tests were not run
+    NotRun,
+    Passed,
+    Failed,
+    BuildFailed,
+    TimedOut,
+}
+
+impl From<TestOutcome> for ExerciseStatus {
+    fn from(outcome: TestOutcome) -> Self {
+        match outcome {
+            TestOutcome::Passed => ExerciseStatus::Passed,
+            TestOutcome::Failed => ExerciseStatus::Failed,
+            TestOutcome::BuildFailed => ExerciseStatus::BuildFailed,
+            TestOutcome::TimedOut => ExerciseStatus::TimedOut,
+        }
+    }
+}
+
+impl fmt::Display for ExerciseStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExerciseStatus::Missing => "missing",
+            ExerciseStatus::NotRun => "not run",
+            ExerciseStatus::Passed => "passed",
+            ExerciseStatus::Failed => "failed",
+            ExerciseStatus::BuildFailed => "build failed",
+            ExerciseStatus::TimedOut => "timed out",
+        })
+    }
+}
+
+impl<'track> ProgressReport<'track> {
+    /// Create a progress report by mapping the directories in the student's exercises folder
+    /// back to the exercises in the track. Directories are matched on the numeric prefix that
+    /// is used when rendering the exercises, so renamed exercises are still found.
+    pub fn create<S: AsRef<Path>>(
+        track: &'track Track,
+        ProgressReportOptions {
+            student_dir,
+            run_tests: should_run_tests,
+            test_opts,
+            on_test_run,
+        }: ProgressReportOptions<'_, S>,
+    ) -> Result<Self, ProgressReportError> {
+        let exercises_dir = student_exercises_dir(student_dir.as_ref());
+
+        let mut modules = vec![];
+        for module in track.modules.iter() {
+            let module_dir = find_numbered_dir(Some(&exercises_dir), module.index)?;
+            let mut units = vec![];
+            for unit in module.data.units.iter() {
+                let unit_dir = find_numbered_dir(module_dir.as_deref(), unit.index)?;
+                let mut exercises = vec![];
+                for (exercise, exercise_index) in unit.data.numbered_exercises() {
+                    let path = find_numbered_dir(unit_dir.as_deref(), exercise_index)?;
+                    let mut progress = ExerciseProgress {
+                        reference: format!("{}.{}.{exercise_index}", module.index, unit.index),
+                        name: &exercise.data.name,
+                        path: path.clone(),
+                        status: ExerciseStatus::Missing,
+                        tests_passed: 0,
+                        tests_failed: 0,
+                        todos: 0,
+                    };
+
+                    if let Some(path) = path {
+                        progress.todos = count_todos(&path)
+                            .into_report()
+                            .change_context(ProgressReportError)?;
+                        progress.status = ExerciseStatus::NotRun;
+                        if should_run_tests {
+                            if let Some(on_test_run) = on_test_run {
+                                on_test_run(&progress.reference);
+                            }
+                            let test_run = run_tests(&path, &test_opts)
+                                .into_report()
+                                .attach_printable_lazy(|| {
+                                    format!("Unable to run tests in {}", path.to_string_lossy())
+                                })
+                                .change_context(ProgressReportError)?;
+                            progress.status = test_run.outcome.into();
+                            progress.tests_passed = test_run.passed;
+                            progress.tests_failed = test_run.failed;
+                        }
+                    }
+
+                    exercises.push(progress);
+                }
+                units.push(UnitProgress {
+                    index: unit.index,
+                    name: &unit.data.name,
+                    exercises,
+                });
+            }
+            modules.push(ModuleProgress {
+                index: module.index,
+                name: &module.data.name,
+                units,
+            });
+        }
+
+        Ok(ProgressReport {
+            track: &track.name,
+            modules,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        writeln!(md, "# Progress report: {}\n", self.track).unwrap();
+
+        for module in self.modules.iter() {
+            let exercises = module.units.iter().flat_map(|u| u.exercises.iter());
+            writeln!(
+                md,
+                "## Module {}: {}\n\n{}\n",
+                module.index,
+                module.name,
+                summarize(exercises)
+            )
+            .unwrap();
+
+            for unit in module.units.iter() {
+                writeln!(
+                    md,
+                    "### Unit {}.{}: {}\n\n{}\n",
+                    module.index,
+                    unit.index,
+                    unit.name,
+                    summarize(unit.exercises.iter())
+                )
+                .unwrap();
+
+                if unit.exercises.is_empty() {
+                    continue;
+                }
+
+                md += "| Exercise | Status | Tests passed | Tests failed | `todo!()`s left |\n";
+                md += "|----------|--------|--------------|--------------|-----------------|\n";
+                for exercise in unit.exercises.iter() {
+                    writeln!(
+                        md,
+                        "| {} {} | {} | {} | {} | {} |",
+                        exercise.reference,
+                        exercise.name,
+                        exercise.status,
+                        exercise.tests_passed,
+                        exercise.tests_failed,
+                        exercise.todos
+                    )
+                    .unwrap();
+                }
+                md += "\n";
+            }
+        }
+
+        md
+    }
+}
+
+fn summarize<'a, 'track: 'a>(
+    exercises: impl Iterator<Item = &'a ExerciseProgress<'track>>,
+) -> String {
+    let (total, passed, todos) = exercises.fold((0, 0, 0), |(total, passed, todos), e| {
+        let is_passed = e.status == ExerciseStatus::Passed;
+        (total + 1, passed + usize::from(is_passed), todos + e.todos)
+    });
+    format!("{passed} of {total} exercises passing, {todos} `todo!()`s left")
+}
+
+/// Find the exercises folder of a student. Accepts both the exercises folder
+/// and the folder containing it.
+pub(crate) fn student_exercises_dir(student_dir: &Path) -> PathBuf {
+    match student_dir.join("exercises") {
+        d if d.is_dir() => d,
+        _ => student_dir.to_path_buf(),
+    }
+}
+
+/// Find the package of exercise `{module_index}.{unit_index}.{exercise_index}` in a
+/// student's exercises folder, by the numeric prefixes that are used when rendering exercises
+pub(crate) fn find_exercise_dir(
+    exercises_dir: &Path,
+    module_index: usize,
+    unit_index: usize,
+    exercise_index: usize,
+) -> Result<Option<PathBuf>, ProgressReportError> {
+    let module_dir = find_numbered_dir(Some(exercises_dir), module_index)?;
+    let unit_dir = find_numbered_dir(module_dir.as_deref(), unit_index)?;
+    find_numbered_dir(unit_dir.as_deref(), exercise_index)
+}
+
+/// Find the directory within `parent` whose name starts with `{index}-`
+fn find_numbered_dir(
+    parent: Option<&Path>,
+    index: usize,
+) -> Result<Option<PathBuf>, ProgressReportError> {
+    let Some(parent) = parent.filter(|p| p.is_dir()) else {
+        return Ok(None);
+    };
+    let prefix = format!("{index}-");
+    let mut dirs = fs::read_dir(parent)
+        .into_report()
+        .attach_printable_lazy(|| format!("Unable to read {}", parent.to_string_lossy()))
+        .change_context(ProgressReportError)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    Ok(dirs.into_iter().next())
+}
@@ -0,0 +1,321 @@
+use std::{fmt, path::Path};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::Serialize;
+
+use crate::{
+    include::resolve_includes,
+    io::{PathExt, WriteExt},
+    RenderReport, RenderedBook, Track,
+};
+
+const SEARCH_PAGE: &str = include_str!("../include/slides/search.html");
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct RenderSearchIndexError;
+
+impl fmt::Display for RenderSearchIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to render search index")
+    }
+}
+
+impl error_stack::Context for RenderSearchIndexError {}
+
+#[derive(Debug, Serialize)]
+struct SearchIndex<'t> {
+    track: &'t str,
+    entries: Vec<SearchEntry<'t>>,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchEntry<'t> {
+    kind: SearchEntryKind,
+    title: &'t str,
+    /// Reference of the unit, like `2.3`
+    unit: String,
+    unit_title: &'t str,
+    url: String,
+    objectives: &'t [String],
+    summary: &'t [String],
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum SearchEntryKind {
+    Slides,
+    Exercise,
+}
+
+/// Write `search-index.json`, with the text of the slides of every topic and the description
+/// of every exercise, and a page to search it into `out_dir/slides/search`.
+/// Slides link to their deck, exercises to their section in the book.
+pub(crate) fn render_search_index(
+    track: &Track,
+    book: &RenderedBook,
+    url_base: &str,
+    out_dir: impl AsRef<Path>,
+) -> Result<RenderReport, RenderSearchIndexError> {
+    let mut report = RenderReport::default();
+    let url_base = url_base.trim_matches('/');
+    let url_base_separator = if url_base.is_empty() { "" } else { "/" };
+
+    let mut entries = vec![];
+    for module in track.modules.iter() {
+        for unit in module.data.units.iter() {
+            let unit_ref = format!("{}.{}", module.index, unit.index);
+            let deck_url = format!(
+                "/{url_base}{url_base_separator}slides/{}_{}/",
+                module.index, unit.index
+            );
+            let book_url = book
+                .unit_pages
+                .get(&(module.index, unit.index))
+                .map(|page| {
+                    format!(
+                        "/{url_base}{url_base_separator}{}",
+                        page.with_extension("html").to_string_lossy()
+                    )
+                });
+
+            let mut numbered_exercises = unit.data.numbered_exercises();
+            for topic in unit.data.topics.iter() {
+                let topic = &topic.data;
+                let content = resolve_includes(
+                    &topic.content.read_to_string()?,
+                    topic.content.parent().unwrap(),
+                )
+                .change_context(RenderSearchIndexError)?;
+                entries.push(SearchEntry {
+                    kind: SearchEntryKind::Slides,
+                    title: &topic.name,
+                    unit: unit_ref.clone(),
+                    unit_title: &unit.data.name,
+                    url: deck_url.clone(),
+                    objectives: &topic.objectives,
+                    summary: &topic.summary,
+                    text: plain_text(&content),
+                });
+
+                let topic_exercises = numbered_exercises.by_ref().take(topic.exercises.len());
+                for (exercise, n) in topic_exercises {
+                    let exercise = &exercise.data;
+                    let Some(book_url) = &book_url else {
+                        continue;
+                    };
+                    let heading = format!("Exercise {unit_ref}.{n}: {}", exercise.name);
+                    let description = resolve_includes(
+                        &exercise.description.read_to_string()?,
+                        exercise.description.parent().unwrap(),
+                    )
+                    .change_context(RenderSearchIndexError)?;
+                    entries.push(SearchEntry {
+                        kind: SearchEntryKind::Exercise,
+                        title: &exercise.name,
+                        unit: unit_ref.clone(),
+                        unit_title: &unit.data.name,
+                        url: format!("{book_url}#{}", mdbook_anchor(&heading)),
+                        objectives: &[],
+                        summary: &[],
+                        text: plain_text(&description),
+                    });
+                }
+            }
+        }
+    }
+
+    let search_dir = out_dir.as_ref().join("slides").join("search");
+    search_dir.create_dir_all()?;
+    let index = serde_json::to_string(&SearchIndex {
+        track: &track.name,
+        entries,
+    })
+    .into_report()
+    .change_context(RenderSearchIndexError)?;
+    report
+        .create_file(search_dir.join("search-index.json"))?
+        .write_all(index)?;
+    report
+        .create_file(search_dir.join("index.html"))?
+        .write_all(SEARCH_PAGE)?;
+
+    let package_json = out_dir.as_ref().join("slides").join("package.json");
+    if package_json.exists() {
+        add_build_search_script(&package_json)?;
+    }
+
+    Ok(report)
+}
+
+/// Add a `build-search` script to the `package.json` of the slides package, which copies the
+/// search page into `dist/search`, and run it as part of `build-all`
+fn add_build_search_script(package_json: &Path) -> Result<(), RenderSearchIndexError> {
+    let mut package: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&package_json.read_to_string()?)
+            .into_report()
+            .attach_printable_lazy(|| format!("Unable to parse {}", package_json.to_string_lossy()))
+            .change_context(RenderSearchIndexError)?;
+    let Some(serde_json::Value::Object(scripts)) = package.get_mut("scripts") else {
+        return Ok(());
+    };
+
+    // Keep the underscore key last, like the slides package does
+    let underscore = scripts.remove("_");
+    scripts.insert(
+        "build-search".into(),
+        "node -e \"require('fs').cpSync('search', 'dist/search', { recursive: true })\"".into(),
+    );
+    if let Some(serde_json::Value::String(build_all)) = scripts.get_mut("build-all") {
+        if !build_all.split(" && ").any(|s| s == "npm run build-search") {
+            *build_all += " && npm run build-search";
+        }
+    }
+    if let Some(underscore) = underscore {
+        scripts.insert("_".into(), underscore);
+    }
+
+    package_json
+        .create_file()?
+        .write_all(serde_json::to_string_pretty(&package).unwrap())
+}
+
+/// Strip comments, slide separators and modmod placeholders from Markdown content
+fn plain_text(markdown: &str) -> String {
+    let markdown = strip_frontmatter(markdown);
+    let mut text = String::with_capacity(markdown.len());
+    let mut rest = markdown.as_str();
+    while let Some(start) = rest.find("<!--") {
+        text += &rest[..start];
+        rest = rest[start..]
+            .find("-->")
+            .map_or("", |end| &rest[start + end + 3..]);
+    }
+    text += rest;
+
+    let mut rest = text.as_str();
+    let mut stripped = String::with_capacity(text.len());
+    while let Some(start) = rest.find("#[modmod:") {
+        stripped += &rest[..start];
+        rest = rest[start..]
+            .find(']')
+            .map_or("", |end| &rest[start + end + 1..]);
+    }
+    stripped += rest;
+
+    stripped
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.is_empty() && *l != "---")
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Remove the frontmatter of the content and of every Slidev slide:
+/// blocks of `key: value` lines between two `---` lines
+fn strip_frontmatter(markdown: &str) -> String {
+    let is_separator = |line: &&str| line.trim_end() == "---";
+    let is_key = |line: &str| {
+        line.split_once(':').is_some_and(|(key, _)| {
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        })
+    };
+    let lines: Vec<_> = markdown.lines().collect();
+    let mut kept = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        if is_separator(&lines[i]) {
+            let block = &lines[i + 1..];
+            if let Some(len) = block.iter().position(is_separator) {
+                let block = &block[..len];
+                let is_frontmatter = block.first().is_some_and(|l| is_key(l))
+                    && block.iter().all(|l| {
+                        is_key(l) || l.starts_with(char::is_whitespace) || l.starts_with("- ")
+                    });
+                if is_frontmatter {
+                    i += len + 2;
+                    continue;
+                }
+            }
+        }
+        kept.push(lines[i]);
+        i += 1;
+    }
+    kept.join("\n")
+}
+
+/// The id mdBook gives to a heading
+fn mdbook_anchor(heading: &str) -> String {
+    heading
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '_' || c == '-' => Some(c.to_ascii_lowercase()),
+            c if c.is_whitespace() => Some('-'),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_strips_markup() {
+        let content = r#"---
+layout: default
+class: text-center
+---
+
+# Pin
+
+![](#[modmod:images]/pin.svg)
+<!--
+- Notes
+-->
+Pinned values can't move
+
+---
+layout: two-cols
+---
+
+# Unpin
+Note: most types are `Unpin`
+"#;
+        assert_eq!(
+            plain_text(content),
+            "# Pin\n![](/pin.svg)\nPinned values can't move\n# Unpin\nNote: most types are `Unpin`"
+        );
+        assert_eq!(
+            mdbook_anchor("Exercise 2.3.1: Ring buffer"),
+            "exercise-231-ring-buffer"
+        );
+    }
+
+    #[test]
+    fn build_search_script_is_added_to_build_all() {
+        let dir = crate::io::test_dir("build-search-script");
+        let package_json = dir.join("package.json");
+        crate::io::write_test_file(
+            &package_json,
+            r#"{ "scripts": { "build-1_1": "slidev build", "build-all": "npm run build-1_1 && npm run build-index", "_": "" } }"#,
+        );
+
+        add_build_search_script(&package_json).unwrap();
+        add_build_search_script(&package_json).unwrap();
+
+        let package: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&package_json).unwrap()).unwrap();
+        let scripts = package["scripts"].as_object().unwrap();
+        assert_eq!(
+            scripts["build-all"],
+            "npm run build-1_1 && npm run build-index && npm run build-search"
+        );
+        assert!(scripts.contains_key("build-search"));
+        assert_eq!(scripts.keys().next_back().unwrap(), "_");
+    }
+}
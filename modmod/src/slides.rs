@@ -1,19 +1,28 @@
 #![allow(dead_code)]
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use error_stack::{IntoReport, Result, ResultExt};
+use error_stack::{IntoReport, Report, Result, ResultExt};
 use serde_json::Value as JsonValue;
 
 type JsonObject = serde_json::Map<String, JsonValue>;
 
 use crate::{
-    io::{copy_files, PathExt, WriteExt},
-    to_prefixed_tag, to_tag,
+    include::resolve_includes,
+    io::{escape_xml, PathExt, WriteExt},
+    load::{Question, Quiz, TrackMetadata},
+    slug::{to_prefixed_tag, to_tag, SlugRegistry},
+    variables::substitute_variables,
+    RenderReport,
 };
 
 const PACKAGE_JSON_CONTENT_STUB: &str = include_str!("../include/slides/package.json");
 const SLIDES_TEMPLATE_DEFAULT: &str = include_str!("../include/slides/default.md");
+const INDEX_PAGE_TEMPLATE: &str = include_str!("../include/slides/index.html");
+const TOPIC_IMAGES_PLACEHOLDER: &str = "#[modmod:topic_images]";
+const QUIZ_PLACEHOLDER: &str = "#[modmod:quiz]";
+const IMAGES_URL_PREFIX: &str = "/images/";
 
 #[derive(Debug, Default)]
 #[non_exhaustive]
@@ -27,25 +36,55 @@ impl fmt::Display for RenderSlidesError {
 
 impl error_stack::Context for RenderSlidesError {}
 
+/// Describes where the decks ended up after rendering
+#[derive(Debug, Default)]
+pub struct RenderedSlides {
+    /// Maps the module and unit index of each unit to the path of its deck,
+    /// relative to the `slides` folder. Units without slides are left out.
+    pub deck_paths: BTreeMap<(usize, usize), PathBuf>,
+    pub report: RenderReport,
+}
+
 pub struct SlidesRenderOptions<'t, 'u, P: AsRef<Path>> {
     pub theme: &'t str,
     pub package_json: Option<P>,
     pub url_base: &'u str,
+    pub image_layout: SlideImageLayout,
+}
+
+/// Determines where the images of each topic end up in the slides package
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SlideImageLayout {
+    /// Copy the images of each topic into `images/<module>/<unit>/<topic>`,
+    /// and rewrite `/images/...` references in the topic content accordingly
+    #[default]
+    PerTopic,
+    /// Copy the images of all topics into a single `images` directory.
+    /// Rendering fails if two topics ship an image with the same name.
+    Flat,
 }
 
 #[derive(Debug)]
 pub struct SlidesPackage<'track> {
     /// Name of the package, corresponds to the name of the track
     name: &'track str,
+    metadata: &'track TrackMetadata,
     decks: Vec<SlideDeck<'track>>,
+    variables: &'track BTreeMap<String, String>,
 }
 
 impl<'track> SlidesPackage<'track> {
-    pub fn builder(name: &'track str) -> SlidesPackageBuilder<'track> {
+    pub fn builder(
+        name: &'track str,
+        metadata: &'track TrackMetadata,
+        variables: &'track BTreeMap<String, String>,
+    ) -> SlidesPackageBuilder<'track> {
         SlidesPackageBuilder {
             package: SlidesPackage {
                 name,
+                metadata,
                 decks: vec![],
+                variables,
             },
         }
     }
@@ -57,8 +96,12 @@ impl<'track> SlidesPackage<'track> {
             theme,
             package_json,
             url_base,
+            image_layout,
         }: SlidesRenderOptions<'_, '_, P>,
-    ) -> Result<(), RenderSlidesError> {
+        slugs: &mut SlugRegistry,
+    ) -> Result<RenderedSlides, RenderSlidesError> {
+        let mut report = RenderReport::default();
+        let mut deck_paths = BTreeMap::new();
         let mut package_json: JsonObject = match package_json {
             Some(p) => serde_json::from_str(&p.read_to_string()?)
                 .into_report()
@@ -67,6 +110,33 @@ impl<'track> SlidesPackage<'track> {
         };
 
         package_json.insert("name".into(), to_tag(self.name).into());
+        let TrackMetadata {
+            authors,
+            version,
+            license,
+            description,
+            repository,
+            url,
+        } = self.metadata;
+        if !authors.is_empty() {
+            package_json.insert("contributors".into(), authors.clone().into());
+        }
+        // npm refuses to install packages with a version that isn't valid semver
+        if let Some(version) = version.as_ref().filter(|v| is_semver(v)) {
+            package_json.insert("version".into(), version.clone().into());
+        }
+        if let Some(license) = license {
+            package_json.insert("license".into(), license.clone().into());
+        }
+        if let Some(description) = description {
+            package_json.insert("description".into(), description.clone().into());
+        }
+        if let Some(repository) = repository {
+            package_json.insert("repository".into(), repository.clone().into());
+        }
+        if let Some(url) = url {
+            package_json.insert("homepage".into(), url.clone().into());
+        }
         let mut package_scripts = JsonObject::new();
 
         let output_dir = out_dir.as_ref();
@@ -77,17 +147,64 @@ impl<'track> SlidesPackage<'track> {
         slide_images_dir.create_dir_all()?;
         let url_base = url_base.trim_matches('/');
         let url_base_separator = if url_base.is_empty() { "" } else { "/" };
+        // Keeps track of where each copied image came from, to detect collisions
+        let mut copied_images: HashMap<PathBuf, &Path> = HashMap::new();
+        let mut image_collisions = vec![];
+        // The decks that were written, with the prefix of their scripts
+        let mut rendered_decks = vec![];
 
         for deck in self.decks.iter() {
             let deck_prefix = format!("{}_{}", deck.module_index, deck.unit_index);
-            let deck_slug = to_prefixed_tag(deck.name, &deck_prefix);
+            let deck_slug = slugs
+                .claim(
+                    "slides",
+                    deck.name,
+                    to_prefixed_tag(deck.name, &deck_prefix),
+                )
+                .change_context(RenderSlidesError::default())?;
             let deck_output = slides_output_dir.join(&deck_slug).with_extension("md");
             let mut unit_content = String::new();
             let mut unit_objectives = String::new();
             let mut unit_summary = String::new();
+            let mut unit_questions = vec![];
+            let mut deck_images = vec![];
 
             for section in deck.sections.iter() {
+                let topic_images_dir = section.path.join("images");
+                let images_subdir = match image_layout {
+                    SlideImageLayout::PerTopic => format!(
+                        "images/{}/{}/{}",
+                        deck.module_index, deck.unit_index, section.index
+                    ),
+                    SlideImageLayout::Flat => "images".to_string(),
+                };
+
+                let mut image_refs = vec![];
+                for image in section.images.iter() {
+                    let image_ref = match image_layout {
+                        SlideImageLayout::PerTopic => image
+                            .strip_prefix(&topic_images_dir)
+                            .unwrap_or(Path::new(image.file_name().unwrap())),
+                        SlideImageLayout::Flat => Path::new(image.file_name().unwrap()),
+                    };
+                    image_refs.push(image_ref.to_string_lossy().replace('\\', "/"));
+                    deck_images.push((*image, Path::new(&images_subdir).join(image_ref)));
+                }
+
                 let topic_content = section.content.read_to_string()?;
+                let topic_content =
+                    resolve_includes(&topic_content, section.content.parent().unwrap())
+                        .attach_printable_lazy(|| {
+                            format!("Error in {}", section.content.to_string_lossy())
+                        })
+                        .change_context(RenderSlidesError::default())?;
+                let topic_content = match image_layout {
+                    SlideImageLayout::PerTopic => {
+                        rewrite_image_refs(&topic_content, &image_refs, &images_subdir)
+                    }
+                    SlideImageLayout::Flat => topic_content,
+                }
+                .replace(TOPIC_IMAGES_PLACEHOLDER, &format!("/{images_subdir}"));
                 let topic_content = topic_content.trim();
 
                 if !topic_content.is_empty() {
@@ -105,13 +222,29 @@ impl<'track> SlidesPackage<'track> {
                 for item in section.summary.iter() {
                     unit_summary += &format!("- {}\n", item.trim());
                 }
+
+                if let Some(quiz) = section.quiz {
+                    unit_questions.extend(quiz.questions.iter());
+                }
             }
 
-            if unit_content.is_empty() && unit_objectives.is_empty() && unit_summary.is_empty() {
+            if unit_content.is_empty()
+                && unit_objectives.is_empty()
+                && unit_summary.is_empty()
+                && unit_questions.is_empty()
+            {
                 continue;
             }
 
-            let mut deck_file = deck_output.create_file()?;
+            let mut deck_file = report.create_file(&deck_output)?;
+            rendered_decks.push((deck, deck_prefix.clone()));
+            deck_paths.insert(
+                (deck.module_index, deck.unit_index),
+                deck_output
+                    .strip_prefix(&slides_output_dir)
+                    .unwrap()
+                    .to_path_buf(),
+            );
 
             {
                 let deck_output_str = deck_output
@@ -127,7 +260,7 @@ impl<'track> SlidesPackage<'track> {
 
                 package_scripts.insert(
                     format!("build-{deck_prefix}"),
-                    format!("slidev build --download --out dist/{deck_slug} --base /{url_base}{url_base_separator}slides/{}_{}/ {deck_output_str}", deck.module_index, deck.unit_index)
+                    format!("slidev build --download --out dist/{deck_prefix} --base /{url_base}{url_base_separator}slides/{deck_prefix}/ {deck_output_str}")
                         .into(),
                 );
                 package_scripts.insert(
@@ -136,14 +269,35 @@ impl<'track> SlidesPackage<'track> {
                 );
             }
 
-            for section in deck.sections.iter() {
-                copy_files(&section.images, &slide_images_dir)?;
+            for (image, image_dest) in deck_images {
+                match copied_images.get(&image_dest) {
+                    Some(&other) if other != image => {
+                        image_collisions.push(format!(
+                            "{} and {} are both copied to {}",
+                            other.to_string_lossy(),
+                            image.to_string_lossy(),
+                            image_dest.to_string_lossy(),
+                        ));
+                        continue;
+                    }
+                    Some(_) => continue,
+                    None => {}
+                }
+                let image_dest_path = slides_output_dir.join(&image_dest);
+                image_dest_path.parent().unwrap().create_dir_all()?;
+                report.copy(image, &image_dest_path)?;
+                copied_images.insert(image_dest, image);
             }
 
-            let template_content = deck
+            let mut template_content = deck
                 .template
                 .map(|t| t.read_to_string())
                 .unwrap_or(Ok(SLIDES_TEMPLATE_DEFAULT.to_string()))?;
+            // Quizzes go at the end of the deck in templates that don't place them
+            if !unit_questions.is_empty() && !template_content.contains(QUIZ_PLACEHOLDER) {
+                template_content =
+                    format!("{}\n\n{QUIZ_PLACEHOLDER}\n", template_content.trim_end());
+            }
             let slides_content = template_content
                 .replace("#[modmod:mod_title]", deck.module_name)
                 .replace("#[modmod:mod_index]", &deck.module_index.to_string())
@@ -152,11 +306,61 @@ impl<'track> SlidesPackage<'track> {
                 .replace("#[modmod:content]", &unit_content)
                 .replace("#[modmod:objectives]", &unit_objectives)
                 .replace("#[modmod:summary]", &unit_summary)
-                .replace("#[modmod:theme]", theme);
+                .replace(QUIZ_PLACEHOLDER, &quiz_slides(&unit_questions))
+                .replace("#[modmod:theme]", theme)
+                .replace("#[modmod:track_title]", self.name)
+                .replace("#[modmod:authors]", &authors.join(", "))
+                .replace("#[modmod:version]", version.as_deref().unwrap_or_default())
+                .replace("#[modmod:license]", license.as_deref().unwrap_or_default())
+                .replace(
+                    "#[modmod:repository]",
+                    repository.as_deref().unwrap_or_default(),
+                );
+            let slides_content =
+                substitute_variables(&slides_content, &[deck.module_variables, self.variables])
+                    .attach_printable_lazy(|| format!("Error in slides of unit {}", deck.name))
+                    .change_context(RenderSlidesError::default())?;
 
             deck_file.write_all(slides_content)?;
         }
 
+        if !image_collisions.is_empty() {
+            let mut error = Report::new(RenderSlidesError::default())
+                .attach_printable("Slide images with the same name would overwrite each other");
+            for collision in image_collisions {
+                error = error.attach_printable(collision);
+            }
+            return Err(error);
+        }
+
+        let index_page = INDEX_PAGE_TEMPLATE
+            .replace("#[modmod:track_title]", &escape_xml(self.name))
+            .replace(
+                "#[modmod:decks]",
+                &index_deck_list(
+                    &rendered_decks,
+                    &format!("/{url_base}{url_base_separator}slides"),
+                ),
+            );
+        report
+            .create_file(slides_output_dir.join("index.html"))?
+            .write_all(index_page)?;
+        package_scripts.insert(
+            "build-index".into(),
+            "node -e \"require('fs').mkdirSync('dist', { recursive: true }); require('fs').copyFileSync('index.html', 'dist/index.html')\"".into(),
+        );
+
+        let run_all = |script: &str| {
+            rendered_decks
+                .iter()
+                .map(|(_, prefix)| format!("npm run {script}-{prefix}"))
+                .collect::<Vec<_>>()
+        };
+        let mut build_all = run_all("build");
+        build_all.push("npm run build-index".into());
+        package_scripts.insert("build-all".into(), build_all.join(" && ").into());
+        package_scripts.insert("export-all".into(), run_all("export").join(" && ").into());
+
         // Add underscore key, so that preceding lines can have a trailing comma
         package_scripts.insert("_".into(), "".into());
 
@@ -175,10 +379,10 @@ impl<'track> SlidesPackage<'track> {
 
         let package_json = serde_json::to_string_pretty(&package_json).unwrap();
         let package_json_file = slides_output_dir.join("package.json");
-        let mut package_json_file = package_json_file.create_file()?;
+        let mut package_json_file = report.create_file(&package_json_file)?;
         package_json_file.write_all(package_json)?;
 
-        Ok(())
+        Ok(RenderedSlides { deck_paths, report })
     }
 }
 
@@ -191,15 +395,21 @@ pub struct SlideDeck<'track> {
     unit_index: usize,
     template: Option<&'track Path>,
     sections: Vec<Section<'track>>,
+    module_variables: &'track BTreeMap<String, String>,
 }
 
 #[derive(Debug)]
 pub struct Section<'track> {
+    /// Index of the topic within the unit
+    index: usize,
+    /// Directory of the topic
+    path: &'track Path,
     content: &'track Path,
     objectives: Vec<&'track str>,
     summary: Vec<&'track str>,
     further_reading: Vec<&'track str>,
     images: Vec<&'track Path>,
+    quiz: Option<&'track Quiz>,
 }
 
 pub struct SlidesPackageBuilder<'track> {
@@ -214,6 +424,7 @@ impl<'track> SlidesPackageBuilder<'track> {
         module_index: usize,
         unit_index: usize,
         template: Option<&'track Path>,
+        module_variables: &'track BTreeMap<String, String>,
     ) -> SlideDeckBuilder<'track, '_> {
         SlideDeckBuilder {
             package_builder: self,
@@ -224,6 +435,7 @@ impl<'track> SlidesPackageBuilder<'track> {
                 unit_index,
                 template,
                 sections: vec![],
+                module_variables,
             },
         }
     }
@@ -239,15 +451,23 @@ pub struct SlideDeckBuilder<'track, 'p> {
 }
 
 impl<'track, 'p> SlideDeckBuilder<'track, 'p> {
-    pub fn section(&mut self, content: &'track Path) -> SlidesSectionBuilder<'track, 'p, '_> {
+    pub fn section(
+        &mut self,
+        index: usize,
+        path: &'track Path,
+        content: &'track Path,
+    ) -> SlidesSectionBuilder<'track, 'p, '_> {
         SlidesSectionBuilder {
             deck_builder: self,
             section: Section {
+                index,
+                path,
                 content,
                 objectives: vec![],
                 summary: vec![],
                 further_reading: vec![],
                 images: vec![],
+                quiz: None,
             },
         }
     }
@@ -280,8 +500,223 @@ impl<'track, 'p, 'd> SlidesSectionBuilder<'track, 'p, 'd> {
         self.section.images.push(image);
     }
 
+    pub fn quiz(&mut self, quiz: &'track Quiz) {
+        self.section.quiz = Some(quiz);
+    }
+
     pub fn add(self) -> &'d mut SlideDeckBuilder<'track, 'p> {
         self.deck_builder.slide_deck.sections.push(self.section);
         self.deck_builder
     }
 }
+
+/// List the decks by module, linking to where their build scripts deploy them
+fn index_deck_list(decks: &[(&SlideDeck, String)], slides_url: &str) -> String {
+    let mut list = String::new();
+    let mut current_module = None;
+    for (deck, prefix) in decks {
+        if current_module != Some(deck.module_index) {
+            if current_module.is_some() {
+                list += "    </ul>\n";
+            }
+            current_module = Some(deck.module_index);
+            list += &format!(
+                "    <h2>Module {}: {}</h2>\n    <ul>\n",
+                deck.module_index,
+                escape_xml(deck.module_name)
+            );
+        }
+        list += &format!(
+            "        <li><a href=\"{slides_url}/{prefix}/\">{}.{} {}</a></li>\n",
+            deck.module_index,
+            deck.unit_index,
+            escape_xml(deck.name)
+        );
+    }
+    if current_module.is_some() {
+        list += "    </ul>";
+    }
+    list
+}
+
+/// Check whether `version` looks like `MAJOR.MINOR.PATCH`, optionally with pre-release
+/// and build metadata
+fn is_semver(version: &str) -> bool {
+    let core = version.split(['-', '+']).next().unwrap();
+    let parts: Vec<_> = core.split('.').collect();
+    parts.len() == 3
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Render one slide per question, revealing the answer on click
+fn quiz_slides(questions: &[&Question]) -> String {
+    let mut slides = String::new();
+    for (question, n) in questions.iter().zip(1..) {
+        slides += &format!("---\n\n# Quiz\n\n**{n}. {}**\n\n", question.question.trim());
+        for (label, choice) in question.labelled_choices() {
+            slides += &format!("- **{label}.** {}\n", choice.trim());
+        }
+        if !question.choices.is_empty() {
+            slides += "\n";
+        }
+        slides += &format!(
+            "<v-click>\n\n**Answer:** {}\n\n",
+            question.labelled_answer().trim()
+        );
+        if let Some(explanation) = &question.explanation {
+            slides += &format!("{}\n\n", explanation.trim());
+        }
+        slides += "</v-click>\n\n";
+    }
+    slides
+}
+
+/// Rewrite `/images/<image>` references to any of the given `images`
+/// so that they point into `images_subdir` instead.
+/// Only Markdown image targets and `src` attributes are rewritten, outside of code blocks.
+/// References to images that are not in `images` are left untouched.
+fn rewrite_image_refs(content: &str, images: &[String], images_subdir: &str) -> String {
+    let mut rewritten = String::with_capacity(content.len());
+    let mut in_code_block = false;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block {
+            rewritten.push_str(line);
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(pos) = rest.find(IMAGES_URL_PREFIX) {
+            let (before, after) = rest.split_at(pos);
+            rewritten.push_str(before);
+            let path = &after[IMAGES_URL_PREFIX.len()..];
+            let image = images
+                .iter()
+                .filter(|image| {
+                    path.strip_prefix(image.as_str()).is_some_and(|tail| {
+                        !tail.starts_with(|c: char| {
+                            c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | '/')
+                        })
+                    })
+                })
+                .max_by_key(|image| image.len());
+            match image {
+                Some(image) if is_image_target(&rewritten) => {
+                    rewritten.push('/');
+                    rewritten.push_str(images_subdir);
+                    rewritten.push('/');
+                    rewritten.push_str(image);
+                    rest = &path[image.len()..];
+                }
+                _ => {
+                    rewritten.push_str(IMAGES_URL_PREFIX);
+                    rest = path;
+                }
+            }
+        }
+        rewritten.push_str(rest);
+    }
+    rewritten
+}
+
+/// Whether a URL that follows `before` is the target of a Markdown image,
+/// like `![Layout](`, or the value of a `src` attribute
+fn is_image_target(before: &str) -> bool {
+    if before.ends_with("src=\"") || before.ends_with("src='") {
+        return true;
+    }
+    let Some(before) = before.strip_suffix("](") else {
+        return false;
+    };
+    before
+        .rfind("![")
+        .is_some_and(|start| !before[start..].contains("]("))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quiz_slides_labels_choices_and_answers() {
+        let choice = Question {
+            question: "Which one?".into(),
+            choices: vec!["a".into(), "b".into()],
+            answer: "b".into(),
+            explanation: Some("Because.\n".into()),
+        };
+        let open = Question {
+            question: "Why?".into(),
+            choices: vec![],
+            answer: "Free".into(),
+            explanation: None,
+        };
+        assert_eq!(
+            quiz_slides(&[&choice, &open]),
+            r#"---
+
+# Quiz
+
+**1. Which one?**
+
+- **A.** a
+- **B.** b
+
+<v-click>
+
+**Answer:** B. b
+
+Because.
+
+</v-click>
+
+---
+
+# Quiz
+
+**2. Why?**
+
+<v-click>
+
+**Answer:** Free
+
+</v-click>
+
+"#
+        );
+    }
+
+    #[test]
+    fn rewrite_image_refs_only_rewrites_known_images() {
+        let content = r#"
+![Layout](/images/layout.svg)
+<img src="/images/layout.svg.bak"/>
+<img src="/images/other.png" class="w-40"/>
+[Layout](/images/layout.svg) and https://example.com/images/layout.svg
+
+```html
+<img src="/images/layout.svg"/>
+```
+"#;
+        let images = ["layout.svg".to_string()];
+        let rewritten = rewrite_image_refs(content, &images, "images/2/1/3");
+        assert_eq!(
+            rewritten,
+            r#"
+![Layout](/images/2/1/3/layout.svg)
+<img src="/images/layout.svg.bak"/>
+<img src="/images/other.png" class="w-40"/>
+[Layout](/images/layout.svg) and https://example.com/images/layout.svg
+
+```html
+<img src="/images/layout.svg"/>
+```
+"#
+        );
+    }
+}
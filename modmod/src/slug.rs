@@ -0,0 +1,154 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Report, Result};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct SlugCollisionError;
+
+impl fmt::Display for SlugCollisionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("multiple items map to the same output path")
+    }
+}
+
+impl error_stack::Context for SlugCollisionError {}
+
+/// Determines what happens when two rendered items end up with the same slug
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SlugCollisionPolicy {
+    /// Append a numeric suffix (`-2`, `-3`, ...) to the slug of every item
+    /// that collides with an item that was rendered earlier
+    #[default]
+    Disambiguate,
+    /// Fail rendering
+    Fail,
+}
+
+/// Keeps track of the slugs that are in use within each output directory,
+/// so that rendered items never silently overwrite each other.
+#[derive(Debug, Default)]
+pub struct SlugRegistry {
+    policy: SlugCollisionPolicy,
+    /// Maps output directories to the slugs in use in them,
+    /// along with the title of the item that claimed each slug
+    claimed: HashMap<PathBuf, HashMap<String, String>>,
+}
+
+impl SlugRegistry {
+    pub fn new(policy: SlugCollisionPolicy) -> Self {
+        Self {
+            policy,
+            claimed: HashMap::new(),
+        }
+    }
+
+    /// Claim `slug` for the item titled `title` in output directory `dir`.
+    /// Returns the slug that the item should use, which differs from `slug`
+    /// if it was disambiguated.
+    pub fn claim(
+        &mut self,
+        dir: impl AsRef<Path>,
+        title: impl Display,
+        slug: String,
+    ) -> Result<String, SlugCollisionError> {
+        let dir = dir.as_ref();
+        let title = title.to_string();
+        let claimed = self.claimed.entry(dir.to_path_buf()).or_default();
+
+        let Some(other) = claimed.get(&slug) else {
+            claimed.insert(slug.clone(), title);
+            return Ok(slug);
+        };
+
+        match self.policy {
+            SlugCollisionPolicy::Fail => {
+                Err(Report::new(SlugCollisionError).attach_printable(format!(
+                    "'{other}' and '{title}' both map to '{slug}' in {dir}",
+                    dir = dir.to_string_lossy()
+                )))
+            }
+            SlugCollisionPolicy::Disambiguate => {
+                let slug = (2..)
+                    .map(|i| format!("{slug}-{i}"))
+                    .find(|s| !claimed.contains_key(s))
+                    .unwrap();
+                claimed.insert(slug.clone(), title);
+                Ok(slug)
+            }
+        }
+    }
+}
+
+pub fn to_prefixed_tag<S, P>(s: S, p: P) -> String
+where
+    S: Display,
+    P: Display,
+{
+    to_tag(format!("{p}-{s}"))
+}
+
+/// Convert `s` into a slug that is safe to use in file names and URLs.
+/// Non-ASCII characters are transliterated, `&` becomes `and`,
+/// and any other run of non-alphanumeric characters becomes a single `-`.
+pub fn to_tag<S>(s: S) -> String
+where
+    S: ToString,
+{
+    let s = s.to_string().replace('&', " and ");
+    let s = deunicode::deunicode(&s);
+    let mut tag = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            c if c.is_ascii_alphanumeric() || c == '_' => tag.push(c.to_ascii_lowercase()),
+            // Drop apostrophes, so that "Rust's" becomes "rusts"
+            '\'' | '`' => {}
+            _ if !tag.is_empty() && !tag.ends_with('-') => tag.push('-'),
+            _ => {}
+        }
+    }
+
+    while tag.ends_with('-') {
+        tag.pop();
+    }
+
+    if tag.is_empty() {
+        tag.push_str("untitled");
+    }
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_tag_strips_special_characters() {
+        assert_eq!(to_tag("Basic syntax"), "basic-syntax");
+        assert_eq!(to_tag("Traits & generics"), "traits-and-generics");
+        assert_eq!(to_tag("Input/Output: why?"), "input-output-why");
+        assert_eq!(to_tag("Crème brûlée's Ünits"), "creme-brulees-units");
+        assert_eq!(to_prefixed_tag("Introduction", "2_1"), "2_1-introduction");
+        assert_eq!(to_tag("???"), "untitled");
+    }
+
+    #[test]
+    fn slug_registry_handles_collisions() {
+        let mut slugs = SlugRegistry::new(SlugCollisionPolicy::Disambiguate);
+        let first = slugs.claim("book/src", "Introduction", to_tag("Introduction"));
+        let second = slugs.claim("book/src", "Introduction", to_tag("Introduction"));
+        let other_dir = slugs.claim("slides", "Introduction", to_tag("Introduction"));
+        assert_eq!(first.unwrap(), "introduction");
+        assert_eq!(second.unwrap(), "introduction-2");
+        assert_eq!(other_dir.unwrap(), "introduction");
+
+        let mut slugs = SlugRegistry::new(SlugCollisionPolicy::Fail);
+        assert!(slugs.claim("book/src", "Intro", to_tag("Intro")).is_ok());
+        assert!(slugs.claim("book/src", "Intro!", to_tag("Intro!")).is_err());
+    }
+}
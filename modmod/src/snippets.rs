@@ -0,0 +1,442 @@
+use std::{
+    collections::BTreeSet,
+    fmt, io,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+
+use crate::{
+    include::resolve_includes,
+    io::{read_in_background, PathExt, WriteExt},
+    Track,
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct CheckSnippetsError;
+
+impl fmt::Display for CheckSnippetsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to check code snippets")
+    }
+}
+
+impl error_stack::Context for CheckSnippetsError {}
+
+pub struct CheckSnippetsOptions<'a, W: AsRef<Path>> {
+    /// The folder the snippets are compiled in
+    pub work_dir: W,
+    /// The edition snippets are compiled with, unless they specify one
+    pub edition: &'a str,
+    /// Kill a snippet if it runs longer than this
+    pub timeout: Duration,
+    /// Called with the location of each snippet before it is checked, like
+    /// `path/to/slides.md:12`, for instance to show progress
+    pub on_check: Option<&'a dyn Fn(&str)>,
+}
+
+/// A ```rust code block in a Markdown file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    /// The line of the first line of code in the Markdown file, starting at 1
+    pub line: usize,
+    pub code: String,
+    pub attrs: SnippetAttrs,
+}
+
+/// The rustdoc annotations of a code block
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnippetAttrs {
+    pub ignore: bool,
+    pub compile_fail: bool,
+    pub should_panic: bool,
+    pub no_run: bool,
+    pub edition: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct SnippetCheck {
+    pub file: PathBuf,
+    pub line: usize,
+    pub outcome: SnippetOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnippetOutcome {
+    Passed,
+    Ignored,
+    /// The snippet uses crates other than the standard library, which can't be compiled
+    /// with `rustc` alone. Contains the names of the crates.
+    Skipped {
+        crates: Vec<String>,
+    },
+    /// The snippet did not behave as expected. Contains the reason and the compiler
+    /// or program output.
+    Failed {
+        reason: String,
+        output: String,
+    },
+}
+
+impl SnippetCheck {
+    pub fn is_failure(&self) -> bool {
+        matches!(self.outcome, SnippetOutcome::Failed { .. })
+    }
+}
+
+/// Compile the Rust code blocks in the slides and exercise descriptions of a track,
+/// like rustdoc compiles doctests. Snippets are compiled with `rustc` alone, so snippets
+/// that fail to compile because they use other crates are skipped.
+pub fn check_snippets<W: AsRef<Path>>(
+    track: &Track,
+    CheckSnippetsOptions {
+        work_dir,
+        edition,
+        timeout,
+        on_check,
+    }: CheckSnippetsOptions<'_, W>,
+) -> Result<Vec<SnippetCheck>, CheckSnippetsError> {
+    let work_dir = work_dir.as_ref();
+    work_dir.create_dir_all()?;
+
+    let mut files = BTreeSet::new();
+    for module in track.modules.iter() {
+        for unit in module.data.units.iter() {
+            for topic in unit.data.topics.iter() {
+                files.insert(topic.data.content.as_path());
+                for exercise in topic.data.exercises.iter() {
+                    files.insert(exercise.data.description.as_path());
+                }
+            }
+        }
+    }
+
+    let mut checks = vec![];
+    for file in files {
+        let content = file.read_to_string()?;
+        for mut snippet in extract_snippets(&content) {
+            if let Some(on_check) = on_check {
+                on_check(&format!("{}:{}", file.to_string_lossy(), snippet.line));
+            }
+            let outcome = if snippet.attrs.ignore {
+                SnippetOutcome::Ignored
+            } else {
+                snippet.code = resolve_includes(&snippet.code, file.parent().unwrap())
+                    .change_context(CheckSnippetsError)?;
+                check_snippet(&snippet, work_dir, edition, timeout)?
+            };
+            checks.push(SnippetCheck {
+                file: file.to_path_buf(),
+                line: snippet.line,
+                outcome,
+            });
+        }
+    }
+
+    Ok(checks)
+}
+
+fn check_snippet(
+    snippet: &Snippet,
+    work_dir: &Path,
+    edition: &str,
+    timeout: Duration,
+) -> Result<SnippetOutcome, CheckSnippetsError> {
+    let source = work_dir.join("snippet.rs");
+    let binary = work_dir.join(format!("snippet{}", std::env::consts::EXE_SUFFIX));
+    source
+        .create_file()?
+        .write_all(wrap_snippet(&snippet.code))?;
+
+    let compiled = Command::new("rustc")
+        .arg("--edition")
+        .arg(snippet.attrs.edition.as_deref().unwrap_or(edition))
+        .args(["--crate-name", "snippet", "--error-format", "json", "-o"])
+        .arg(&binary)
+        .arg(&source)
+        .output()
+        .into_report()
+        .attach_printable("Unable to run rustc")
+        .change_context(CheckSnippetsError)?;
+    let diagnostics: Vec<serde_json::Value> = String::from_utf8_lossy(&compiled.stderr)
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let compiler_output: String = diagnostics
+        .iter()
+        .filter_map(|d| d["rendered"].as_str())
+        .collect();
+    if !compiled.status.success() {
+        let crates = missing_crates(&diagnostics);
+        if !crates.is_empty() {
+            return Ok(SnippetOutcome::Skipped {
+                crates: crates.into_iter().collect(),
+            });
+        }
+    }
+
+    let failed = |reason: &str, output: String| SnippetOutcome::Failed {
+        reason: reason.to_string(),
+        output,
+    };
+    let outcome = match (snippet.attrs.compile_fail, compiled.status.success()) {
+        (true, true) => failed("compiled, but should have failed to compile", String::new()),
+        (true, false) => SnippetOutcome::Passed,
+        (false, false) => failed("failed to compile", compiler_output),
+        (false, true) if snippet.attrs.no_run => SnippetOutcome::Passed,
+        (false, true) => {
+            let run = run_with_timeout(Command::new(&binary).current_dir(work_dir), timeout)
+                .into_report()
+                .change_context(CheckSnippetsError)?;
+            match (run, snippet.attrs.should_panic) {
+                (None, _) => failed("timed out", String::new()),
+                (Some(run), should_panic) if run.status.success() == should_panic => {
+                    let output = String::from_utf8_lossy(&run.stderr).into_owned();
+                    if should_panic {
+                        failed("ran successfully, but should have panicked", output)
+                    } else {
+                        failed("panicked or exited unsuccessfully", output)
+                    }
+                }
+                (Some(_), _) => SnippetOutcome::Passed,
+            }
+        }
+    };
+    Ok(outcome)
+}
+
+/// The crates other than the standard library that rustc could not find, judging by
+/// the unresolved imports and paths it reports, like ``unresolved import `serde` ``.
+/// Names rustc suggests importing, like `thread` for `std::thread`, are not crates.
+fn missing_crates(diagnostics: &[serde_json::Value]) -> BTreeSet<String> {
+    let suggests_import = |d: &serde_json::Value| {
+        d["children"].as_array().is_some_and(|children| {
+            children.iter().any(|c| {
+                c["message"]
+                    .as_str()
+                    .is_some_and(|m| m.starts_with("consider importing"))
+            })
+        })
+    };
+    diagnostics
+        .iter()
+        .filter(|d| matches!(d["code"]["code"].as_str(), Some("E0432" | "E0433")))
+        .filter(|d| !suggests_import(d))
+        .filter_map(|d| {
+            // Messages about a path name more than one item, like ``could not find `a` in `b` ``
+            let mut names = d["message"].as_str()?.split('`').skip(1).step_by(2);
+            let name = names.next()?;
+            let is_crate_name = names.next().is_none()
+                && name.starts_with(|c: char| c.is_ascii_lowercase())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !matches!(name, "std" | "core" | "alloc");
+            is_crate_name.then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Run `command`, killing it if it takes longer than `timeout`.
+/// Returns `None` if the command timed out.
+fn run_with_timeout(command: &mut Command, timeout: Duration) -> io::Result<Option<Output>> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Drain stderr while waiting, so that the snippet doesn't block on a full pipe
+    let stderr = read_in_background(child.stderr.take().unwrap());
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() > timeout {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    Ok(Some(Output {
+        status,
+        stdout: vec![],
+        stderr: stderr.recv().unwrap()?.into_bytes(),
+    }))
+}
+
+/// Find the ```rust code blocks in Markdown content
+pub fn extract_snippets(content: &str) -> Vec<Snippet> {
+    let mut snippets = vec![];
+    let mut lines = content.lines().enumerate();
+    while let Some((_, line)) = lines.next() {
+        let trimmed = line.trim_start();
+        let fence_char = match trimmed.chars().next() {
+            Some(c @ ('`' | '~')) => c,
+            _ => continue,
+        };
+        let fence_len = trimmed.chars().take_while(|&c| c == fence_char).count();
+        if fence_len < 3 {
+            continue;
+        }
+        let info = &trimmed[fence_len..];
+        let mut code = String::new();
+        let mut first_line = None;
+        for (i, line) in lines.by_ref() {
+            let trimmed = line.trim_start();
+            let closing_len = trimmed.chars().take_while(|&c| c == fence_char).count();
+            if closing_len >= fence_len && trimmed[closing_len..].trim().is_empty() {
+                break;
+            }
+            first_line.get_or_insert(i + 1);
+            code += line;
+            code += "\n";
+        }
+        if let Some(attrs) = parse_info_string(info) {
+            snippets.push(Snippet {
+                line: first_line.unwrap_or_default(),
+                code,
+                attrs,
+            });
+        }
+    }
+    snippets
+}
+
+/// Parse the info string of a code block, returning `None` if it's not a Rust code block
+fn parse_info_string(info: &str) -> Option<SnippetAttrs> {
+    // Strip Slidev line highlighting, like ```rust {2,3}
+    let info = info.split('{').next().unwrap();
+    let mut words = info
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|w| !w.is_empty());
+    if words.next()? != "rust" {
+        return None;
+    }
+    let mut attrs = SnippetAttrs::default();
+    for word in words {
+        match word {
+            "ignore" => attrs.ignore = true,
+            "compile_fail" => attrs.compile_fail = true,
+            "should_panic" => attrs.should_panic = true,
+            "no_run" => attrs.no_run = true,
+            w if w.starts_with("edition") => attrs.edition = Some(w["edition".len()..].into()),
+            _ => {}
+        }
+    }
+    Some(attrs)
+}
+
+/// Turn a snippet into a program, like rustdoc does with doctests: lines hidden with `# `
+/// are included, and the code is wrapped in `fn main` unless it defines one.
+pub fn wrap_snippet(code: &str) -> String {
+    let mut crate_attrs = String::from("#![allow(unused)]\n");
+    let mut body = String::new();
+    for line in code.lines() {
+        let line = match line.trim_start() {
+            "#" => "",
+            l if l.starts_with("# ") => &l[2..],
+            _ => line,
+        };
+        if line.trim_start().starts_with("#![") {
+            crate_attrs += line;
+            crate_attrs += "\n";
+        } else {
+            body += line;
+            body += "\n";
+        }
+    }
+
+    if body.contains("fn main") {
+        crate_attrs + &body
+    } else {
+        format!("{crate_attrs}fn main() {{\n{body}}}\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::test_dir;
+
+    #[test]
+    fn check_snippet_drains_stderr() {
+        let snippet = Snippet {
+            line: 1,
+            code: "for _ in 0..20_000 {\n    eprintln!(\"{}\", \"x\".repeat(100));\n}\n".into(),
+            attrs: SnippetAttrs::default(),
+        };
+        let work_dir = test_dir("snippets");
+        let outcome = check_snippet(&snippet, &work_dir, "2021", Duration::from_secs(30)).unwrap();
+        assert_eq!(outcome, SnippetOutcome::Passed);
+    }
+
+    #[test]
+    fn check_snippet_skips_snippets_using_other_crates() {
+        let work_dir = test_dir("snippets-crates");
+        let check = |code: &str| {
+            let snippet = Snippet {
+                line: 1,
+                code: code.into(),
+                attrs: SnippetAttrs::default(),
+            };
+            check_snippet(&snippet, &work_dir, "2021", Duration::from_secs(30)).unwrap()
+        };
+
+        assert_eq!(
+            check("use serde::Serialize;\nlet (a, b) = rayon::join(|| 1, || 2);\n"),
+            SnippetOutcome::Skipped {
+                crates: vec!["rayon".into(), "serde".into()]
+            }
+        );
+        let outcome = check(
+            "use std::colections::HashMap;\nmod local {}\nuse local::Thing;\nthread::yield_now();\n",
+        );
+        assert!(
+            matches!(&outcome, SnippetOutcome::Failed { output, .. } if output.contains("colections")),
+            "{outcome:?}"
+        );
+    }
+
+    #[test]
+    fn extract_snippets_parses_attributes_and_lines() {
+        let content = r#"# Slide
+
+```rust {2}
+let x = 1;
+```
+
+```toml
+a = 1
+```
+
+````rust,should_panic
+```
+panic!();
+````
+"#;
+        let snippets = extract_snippets(content);
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets[0].line, 4);
+        assert_eq!(snippets[0].code, "let x = 1;\n");
+        assert_eq!(snippets[0].attrs, SnippetAttrs::default());
+        assert_eq!(snippets[1].line, 12);
+        assert_eq!(snippets[1].code, "```\npanic!();\n");
+        assert!(snippets[1].attrs.should_panic);
+    }
+
+    #[test]
+    fn wrap_snippet_wraps_code_without_main() {
+        assert_eq!(
+            wrap_snippet("#![deny(warnings)]\n# use std::fmt;\nlet x = 1;\n"),
+            "#![allow(unused)]\n#![deny(warnings)]\nfn main() {\nuse std::fmt;\nlet x = 1;\n}\n"
+        );
+        assert_eq!(
+            wrap_snippet("fn main() {}\n"),
+            "#![allow(unused)]\nfn main() {}\n"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::{collections::BTreeMap, fmt};
+
+use error_stack::{Report, Result};
+
+const VARIABLE_PLACEHOLDER_PREFIX: &str = "#[modmod:var.";
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct UnknownVariableError;
+
+impl fmt::Display for UnknownVariableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown variable")
+    }
+}
+
+impl error_stack::Context for UnknownVariableError {}
+
+/// Replace `#[modmod:var.<name>]` placeholders in `content` with the value of variable `<name>`.
+/// Variables are looked up in `scopes` in order, so that earlier scopes override later ones.
+pub(crate) fn substitute_variables(
+    content: &str,
+    scopes: &[&BTreeMap<String, String>],
+) -> Result<String, UnknownVariableError> {
+    let mut substituted = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find(VARIABLE_PLACEHOLDER_PREFIX) {
+        let after_prefix = &rest[start + VARIABLE_PLACEHOLDER_PREFIX.len()..];
+        let Some(end) = after_prefix.find(']') else {
+            break;
+        };
+        substituted += &rest[..start];
+        let name = &after_prefix[..end];
+        let Some(value) = scopes.iter().find_map(|scope| scope.get(name)) else {
+            return Err(Report::new(UnknownVariableError)
+                .attach_printable(format!("Variable '{name}' is not defined")));
+        };
+        substituted += value;
+        rest = &after_prefix[end + 1..];
+    }
+    substituted += rest;
+    Ok(substituted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitute_variables_prefers_earlier_scopes() {
+        let track = BTreeMap::from([
+            ("trainer".to_string(), "Ferris".to_string()),
+            ("venue".to_string(), "Online".to_string()),
+        ]);
+        let module = BTreeMap::from([("venue".to_string(), "Delft".to_string())]);
+
+        assert_eq!(
+            substitute_variables(
+                "#[modmod:var.trainer] in #[modmod:var.venue]",
+                &[&module, &track]
+            )
+            .unwrap(),
+            "Ferris in Delft"
+        );
+        assert!(substitute_variables("#[modmod:var.client]", &[&module, &track]).is_err());
+        assert_eq!(
+            substitute_variables("#[modmod:var.trainer", &[&track]).unwrap(),
+            "#[modmod:var.trainer"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use serde::Deserialize;
+
+use crate::{
+    exercises::RenderedExercises,
+    io::{run_command, PathExt, WriteExt},
+    RenderReport,
+};
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct VendorError;
+
+impl fmt::Display for VendorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to vendor exercise dependencies")
+    }
+}
+
+impl error_stack::Context for VendorError {}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VendorOptions {
+    /// Only use crates that are in the local cargo cache, or in a registry mirror
+    /// configured through source replacement, instead of downloading them
+    pub offline: bool,
+}
+
+/// Collect the dependencies of all rendered exercise packages into `exercises/vendor`,
+/// and write an `exercises/.cargo/config.toml` that replaces crates.io with that folder.
+/// Afterwards, checks that the dependencies of each exercise package build offline.
+pub fn vendor_dependencies(
+    exercises: &RenderedExercises,
+    output_dir: &Path,
+    VendorOptions { offline }: VendorOptions,
+) -> Result<RenderReport, VendorError> {
+    let exercises_dir = output_dir.join("exercises");
+    let mut manifests: Vec<PathBuf> = exercises
+        .exercise_paths
+        .values()
+        .map(|p| p.strip_prefix("exercises").unwrap().join("Cargo.toml"))
+        .filter(|m| exercises_dir.join(m).is_file())
+        .collect();
+    manifests.sort();
+    let Some((first, rest)) = manifests.split_first() else {
+        return Ok(RenderReport::default());
+    };
+
+    let mut command = Command::new("cargo");
+    command
+        .current_dir(&exercises_dir)
+        .args(["vendor", "--respect-source-config", "--manifest-path"])
+        .arg(first);
+    for manifest in rest {
+        command.arg("--sync").arg(manifest);
+    }
+    if offline {
+        command.arg("--offline");
+    }
+    command.arg("vendor");
+    // `cargo vendor` prints the source replacement config needed to use the vendored crates
+    let config = run_command(&mut command)?.stdout;
+    let mut report = RenderReport::default();
+    if !config.is_empty() {
+        let cargo_dir = exercises_dir.join(".cargo");
+        cargo_dir.create_dir_all()?;
+        report
+            .create_file(cargo_dir.join("config.toml"))?
+            .write_all(config)?;
+    }
+    let vendor_dir = exercises_dir.join("vendor");
+    if !vendor_dir.exists() {
+        return Ok(report);
+    }
+    report.files.extend(
+        vendor_dir
+            .get_dir_content()?
+            .files
+            .into_iter()
+            .map(PathBuf::from),
+    );
+
+    // Exercise scaffolding often doesn't compile until the student has worked on it,
+    // so only errors in the vendored dependencies fail the check.
+    // Build outside the output directory, to keep build artifacts out of the exercises.
+    let vendor_dir = vendor_dir
+        .canonicalize()
+        .into_report()
+        .change_context(VendorError)?;
+    let target_dir = std::env::temp_dir().join(format!("modmod-vendor-{}", std::process::id()));
+    let checked = manifests.iter().try_for_each(|manifest| {
+        let package_dir = exercises_dir.join(manifest.parent().unwrap());
+        check_offline(&package_dir, &vendor_dir, &target_dir).attach_printable_lazy(|| {
+            format!(
+                "Dependencies of exercise {} don't build offline",
+                package_dir.to_string_lossy()
+            )
+        })
+    });
+    std::fs::remove_dir_all(&target_dir).ok();
+    checked.map(|_| report)
+}
+
+/// A message printed by `cargo check --message-format json`
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    manifest_path: Option<PathBuf>,
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Deserialize)]
+struct CompilerMessage {
+    level: String,
+}
+
+/// Run `cargo check --offline` in `package_dir`, building in `target_dir`. Succeeds if the build
+/// only failed on errors in exercise code, rather than in crates from `vendor_dir`
+fn check_offline(
+    package_dir: &Path,
+    vendor_dir: &Path,
+    target_dir: &Path,
+) -> Result<(), VendorError> {
+    let output = Command::new("cargo")
+        .current_dir(package_dir)
+        .args(["check", "--offline", "--message-format", "json"])
+        .env("CARGO_TARGET_DIR", target_dir)
+        .output()
+        .into_report()
+        .attach_printable("Unable to run cargo")
+        .change_context(VendorError)?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let error_manifests: Vec<_> = stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+        .filter(|m| m.reason == "compiler-message")
+        .filter(|m| m.message.as_ref().is_some_and(|m| m.level == "error"))
+        .filter_map(|m| m.manifest_path)
+        .collect();
+    // Cargo fails before compiling anything if dependencies can't be resolved
+    if !error_manifests.is_empty() && !error_manifests.iter().any(|m| m.starts_with(vendor_dir)) {
+        return Ok(());
+    }
+
+    Err(Report::new(VendorError)
+        .attach_printable(format!(
+            r#"Stderr: "{}""#,
+            String::from_utf8_lossy(&output.stderr)
+        ))
+        .attach_printable(output.status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{test_dir, write_test_file};
+
+    fn write_package(dir: &Path, name: &str, dependency: &str, lib_rs: &str) {
+        write_test_file(
+            dir.join("Cargo.toml"),
+            &format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n{dependency}\n"
+            ),
+        );
+        write_test_file(dir.join("src/lib.rs"), lib_rs);
+    }
+
+    #[test]
+    fn check_offline_ignores_errors_in_exercise_code_only() {
+        let dir = test_dir("vendor-check").canonicalize().unwrap();
+        let vendor_dir = dir.join("vendor");
+        let target_dir = dir.join("target");
+        write_package(&vendor_dir.join("good"), "good", "", "pub fn f() {}\n");
+        write_package(
+            &vendor_dir.join("bad"),
+            "bad",
+            "",
+            "pub fn f() -> u8 { \"\" }\n",
+        );
+
+        let unfinished = dir.join("unfinished");
+        write_package(
+            &unfinished,
+            "unfinished",
+            r#"good = { path = "../vendor/good" }"#,
+            "mod missing;\n",
+        );
+        assert!(check_offline(&unfinished, &vendor_dir, &target_dir).is_ok());
+
+        let broken_dependency = dir.join("broken-dependency");
+        write_package(
+            &broken_dependency,
+            "broken-dependency",
+            r#"bad = { path = "../vendor/bad" }"#,
+            "",
+        );
+        assert!(check_offline(&broken_dependency, &vendor_dir, &target_dir).is_err());
+
+        let missing_dependency = dir.join("missing-dependency");
+        write_package(
+            &missing_dependency,
+            "missing-dependency",
+            r#"missing = { path = "../vendor/missing" }"#,
+            "",
+        );
+        assert!(check_offline(&missing_dependency, &vendor_dir, &target_dir).is_err());
+    }
+}